@@ -16,7 +16,7 @@
 // Use of this source code is governed by an ISC
 // license that can be found in the LICENSE file.
 
-use crate::hash::H256;
+use crate::hash::{blake256d, H256};
 
 // fast_merkle_root treats the provided slice of hashes as leaves of a merkle tree
 // and returns the resulting merkle root.
@@ -79,6 +79,127 @@ pub fn fast_merkle_root(mut leaves: Vec<H256>) -> H256 {
     leaves[0]
 }
 
+// fast_merkle_path returns the root fast_merkle_root computes over items along
+// with the inclusion path for items[index]: the sibling of the node on the
+// way up at each level, from the leaves to just below the root. A node left
+// without a right child at an odd level is its own sibling, exactly as
+// fast_merkle_root pairs it with itself.
+//
+// Panics if index is out of range.
+pub fn fast_merkle_path(items: &[H256], index: usize) -> (H256, Vec<H256>) {
+    assert!(index < items.len(), "index out of range");
+
+    let mut level = items.to_vec();
+    let mut index = index;
+    let mut path = Vec::with_capacity(tree_height(items.len()).saturating_sub(1));
+    while level.len() > 1 {
+        if level.len() & 1 != 0 {
+            level.push(level[level.len() - 1]);
+        }
+        path.push(level[index ^ 1]);
+        level = level
+            .chunks(2)
+            .map(|pair| pair[0].hash_with(pair[1]))
+            .collect();
+        index /= 2;
+    }
+
+    (level[0], path)
+}
+
+// verify_merkle_path checks that leaf sits at index in a tree of n_leaves
+// leaves under root, given the path fast_merkle_path produced for it. The low
+// bit of index at each level says whether the running node is the left (0) or
+// right (1) child.
+//
+// The leaf count must come from the verifier, not the prover: since an odd
+// node is paired with itself, the path of the last leaf also hashes up to the
+// root from the positions of the copies past the end, so an index is only
+// meaningful once checked against n_leaves.
+pub fn verify_merkle_path(
+    root: H256,
+    leaf: H256,
+    index: usize,
+    n_leaves: usize,
+    path: &[H256],
+) -> bool {
+    if index >= n_leaves || path.len() != tree_height(n_leaves) - 1 {
+        return false;
+    }
+
+    let mut node = leaf;
+    let mut index = index;
+    for sibling in path {
+        node = if index & 1 == 0 {
+            node.hash_with(*sibling)
+        } else {
+            sibling.hash_with(node)
+        };
+        index >>= 1;
+    }
+
+    index == 0 && node == root
+}
+
+// tree_height returns the number of levels, leaves and root included, of the
+// tree fast_merkle_root builds over n_leaves leaves.
+pub fn tree_height(n_leaves: usize) -> usize {
+    let mut n = n_leaves;
+    let mut height = if n == 0 { 0 } else { 1 };
+    while n > 1 {
+        n = n.div_ceil(2);
+        height += 1;
+    }
+    height
+}
+
+// total_nodes returns the number of distinct nodes, leaves and root included,
+// of the tree fast_merkle_root builds over n_leaves leaves. Each level holds
+// half the nodes of the one below rounded up; the copies made to pair an odd
+// node with itself are not counted. This is the number of hashes a store of
+// the whole tree needs room for.
+pub fn total_nodes(n_leaves: usize) -> usize {
+    let mut n = n_leaves;
+    let mut total = n;
+    while n > 1 {
+        n = n.div_ceil(2);
+        total += n;
+    }
+    total
+}
+
+// fast_merkle_root_btc computes a merkle root the way Bitcoin does for the
+// transactions of a block: each parent is the double hash h(h(left + right))
+// of its children, with blake256 standing in for sha256, and an odd level
+// has its last node paired with itself.
+//
+// Beware this layout is malleable (CVE-2012-2459): the lists [a b c] and
+// [a b c c] produce the same root, as do any two lists that differ only by
+// such a duplicated tail at some level. Callers validating blocks must
+// reject duplicate leaves themselves rather than rely on the root.
+pub fn fast_merkle_root_btc(hashes: &[H256]) -> H256 {
+    if hashes.is_empty() {
+        return H256::zero();
+    }
+
+    let mut level = hashes.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let left = pair[0];
+                let right = if pair.len() == 2 { pair[1] } else { pair[0] };
+                let mut concat = [0u8; 64];
+                concat[..32].copy_from_slice(left.as_bytes());
+                concat[32..].copy_from_slice(right.as_bytes());
+                blake256d(&concat)
+            })
+            .collect();
+    }
+
+    level[0]
+}
+
 #[test]
 fn test_to_merkle_fast_short() {
     let _inputs = vec![
@@ -100,3 +221,103 @@ fn test_to_merkle_fast_short() {
 fn test_to_merkle_fast_zero() {
     assert_eq!(fast_merkle_root(vec![H256::zero()]), H256::zero());
 }
+
+#[test]
+fn test_to_merkle_fast_btc() {
+    use crate::hash::blake256;
+
+    let double = |l: H256, r: H256| {
+        let mut concat = l.to_vec();
+        concat.extend_from_slice(r.as_bytes());
+        blake256(blake256(&concat).as_bytes())
+    };
+    let a = blake256(b"tx1");
+    let b = blake256(b"tx2");
+    let c = blake256(b"tx3");
+
+    assert_eq!(fast_merkle_root_btc(&[]), H256::zero());
+    assert_eq!(fast_merkle_root_btc(&[a]), a);
+    assert_eq!(fast_merkle_root_btc(&[a, b]), double(a, b));
+    assert_eq!(
+        fast_merkle_root_btc(&[a, b, c]),
+        double(double(a, b), double(c, c))
+    );
+
+    // CVE-2012-2459, duplicating the odd tail leaves the root unchanged
+    assert_eq!(
+        fast_merkle_root_btc(&[a, b, c]),
+        fast_merkle_root_btc(&[a, b, c, c])
+    );
+}
+
+#[test]
+fn test_merkle_path() {
+    use crate::hash::blake256;
+
+    let items: Vec<H256> = (0..6u8).map(|i| blake256(&[i])).collect();
+    let root = fast_merkle_root(items.clone());
+
+    for (i, item) in items.iter().enumerate() {
+        let (path_root, path) = fast_merkle_path(&items, i);
+        assert_eq!(path_root, root);
+        assert_eq!(path.len(), tree_height(items.len()) - 1);
+        assert!(verify_merkle_path(root, *item, i, items.len(), &path));
+
+        assert!(!verify_merkle_path(root, *item, i ^ 1, items.len(), &path));
+        assert!(!verify_merkle_path(
+            root,
+            blake256(b"other"),
+            i,
+            items.len(),
+            &path
+        ));
+        assert!(!verify_merkle_path(root, *item, i + 8, items.len(), &path));
+    }
+
+    // A lone leaf is its own root with an empty path
+    let (lone_root, path) = fast_merkle_path(&items[..1], 0);
+    assert_eq!(lone_root, items[0]);
+    assert!(path.is_empty());
+    assert!(verify_merkle_path(lone_root, items[0], 0, 1, &path));
+    assert!(!verify_merkle_path(lone_root, items[0], 0, 0, &path));
+}
+
+#[test]
+fn test_merkle_path_phantom_index() {
+    use crate::hash::blake256;
+
+    // The last leaf of an odd level is paired with itself, so its path also
+    // hashes up to the root from the positions past the end
+    for n in [3usize, 5, 6] {
+        let items: Vec<H256> = (0..n as u8).map(|i| blake256(&[i])).collect();
+        let last = n - 1;
+        let (root, path) = fast_merkle_path(&items, last);
+        assert!(verify_merkle_path(root, items[last], last, n, &path));
+
+        for phantom in n..8 {
+            assert!(!verify_merkle_path(root, items[last], phantom, n, &path));
+        }
+
+        // A path of the wrong length for the tree is rejected as well
+        let mut long = path.clone();
+        long.push(root);
+        assert!(!verify_merkle_path(root, items[last], last, n, &long));
+    }
+}
+
+#[test]
+fn test_tree_size() {
+    let expected = [
+        (0, 0, 0),
+        (1, 1, 1),
+        (2, 2, 3),
+        (3, 3, 6),
+        (4, 3, 7),
+        (7, 4, 14),
+        (8, 4, 15),
+    ];
+    for &(n, height, nodes) in expected.iter() {
+        assert_eq!(tree_height(n), height, "height of {}", n);
+        assert_eq!(total_nodes(n), nodes, "nodes of {}", n);
+    }
+}
@@ -30,10 +30,15 @@
 /// // Permute the values in place with Fisher-Yates
 /// fisher_yates(&mut a);
 /// ```
+#[cfg(all(feature = "rand_os", feature = "rand"))]
 pub fn fisher_yates<T>(arr: &mut [T]) {
-    use rand::Rng;
+    fisher_yates_with_rng(arr, &mut crate::mohan_rand());
+}
+
+/// The in place Fisher-Yates shuffle, drawing from `rng`.
+#[cfg(feature = "rand")]
+pub fn fisher_yates_with_rng<T, R: rand::Rng>(arr: &mut [T], rng: &mut R) {
     let n = arr.len();
-    let mut rng = crate::mohan_rand();
 
     for i in 0..n {
         // Swap i with a random point after it
@@ -42,7 +47,59 @@ pub fn fisher_yates<T>(arr: &mut [T]) {
     }
 }
 
+/// A random permutation of `0..n`, the shuffle `fisher_yates_with_rng`
+/// would perform with the same `rng`. Position `k` of any slice of length
+/// `n` ends up holding the element from index `perm[k]`, so the same
+/// permutation can be applied to several parallel slices.
+#[cfg(feature = "rand")]
+pub fn fisher_yates_indexed<R: rand::Rng>(n: usize, rng: &mut R) -> Vec<usize> {
+    let mut perm = (0..n).collect::<Vec<_>>();
+    fisher_yates_with_rng(&mut perm, rng);
+    perm
+}
+
+/// A Fisher-Yates shuffle advanced one swap at a time, with the random
+/// values supplied by the caller. Feeding it the values `fisher_yates`
+/// would have drawn performs the same permutation, and replaying a
+/// recorded sequence of values reproduces it exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FisherYatesState {
+    i: usize,
+    n: usize,
+}
+
+impl FisherYatesState {
+    /// Start a shuffle of a slice of length `n`
+    pub fn new(n: usize) -> FisherYatesState {
+        FisherYatesState { i: 0, n }
+    }
+
+    /// Exclusive upper bound for the `rng_value` of the next `step`
+    pub fn bound(&self) -> usize {
+        self.n - self.i
+    }
 
+    /// Swap position `i` with position `i + rng_value` and move on to the
+    /// next position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arr` is not the length the state was created with, if the
+    /// shuffle is done or if `rng_value` is not below `bound()`.
+    pub fn step<T>(&mut self, arr: &mut [T], rng_value: usize) {
+        assert_eq!(arr.len(), self.n, "slice length changed mid shuffle");
+        assert!(rng_value < self.bound(), "rng_value out of range");
+        arr.swap(self.i, self.i + rng_value);
+        self.i += 1;
+    }
+
+    /// Has every position been swapped?
+    pub fn is_done(&self) -> bool {
+        self.i == self.n
+    }
+}
+
+#[cfg(all(feature = "rand_os", feature = "rand"))]
 #[test]
 fn test_in_place_fisher_yates() {
     let mut a = (0..10).collect::<Vec<_>>();
@@ -52,6 +109,7 @@ fn test_in_place_fisher_yates() {
     }
 }
 
+#[cfg(all(feature = "rand_os", feature = "rand"))]
 #[test]
 fn test_vector_shuffle() {
     let a = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
@@ -59,3 +117,57 @@ fn test_vector_shuffle() {
     fisher_yates(&mut b);
     assert!(a != b);
 }
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_fisher_yates_indexed() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let data = (100..120).collect::<Vec<_>>();
+    let perm = fisher_yates_indexed(data.len(), &mut StdRng::seed_from_u64(7));
+    let applied = perm.iter().map(|&k| data[k]).collect::<Vec<_>>();
+
+    let mut shuffled = data.clone();
+    fisher_yates_with_rng(&mut shuffled, &mut StdRng::seed_from_u64(7));
+    assert_eq!(applied, shuffled);
+
+    let mut sorted = perm.clone();
+    sorted.sort();
+    assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+    assert!(fisher_yates_indexed(0, &mut StdRng::seed_from_u64(7)).is_empty());
+}
+
+#[cfg(all(feature = "rand_os", feature = "rand"))]
+#[test]
+fn test_fisher_yates_state_replay() {
+    use rand::Rng;
+    let mut rng = crate::mohan_rand();
+
+    let mut a = (0..20).collect::<Vec<_>>();
+    let mut state = FisherYatesState::new(a.len());
+    let mut revealed = vec![];
+    while !state.is_done() {
+        let v = rng.gen_range(0..state.bound());
+        revealed.push(v);
+        state.step(&mut a, v);
+    }
+
+    let mut b = (0..20).collect::<Vec<_>>();
+    let mut replay = FisherYatesState::new(b.len());
+    for v in revealed {
+        replay.step(&mut b, v);
+    }
+    assert!(replay.is_done());
+    assert_eq!(a, b);
+
+    let mut sorted = a.clone();
+    sorted.sort();
+    assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+}
+
+#[test]
+#[should_panic]
+fn test_fisher_yates_state_out_of_range() {
+    let mut a = [1, 2, 3];
+    FisherYatesState::new(a.len()).step(&mut a, 3);
+}
@@ -15,9 +15,13 @@
 //! Hash Functions
 
 mod types;
-pub use types::{DefaultHashable, HashWriter, Hashed, H256};
+pub use types::{BitArray, DefaultHashable, HashWriter, Hashed, H256, H512};
 
+mod siphash;
+pub use siphash::{SipBuildHasher, SipHasher24, SipKey};
 
+
+use crate::blake2::many::{hash_many, HashManyJob};
 use crate::blake2::{Params, State};
 use crate::dalek::ristretto::RistrettoPoint;
 
@@ -34,11 +38,29 @@ pub fn blake160(data: &[u8]) -> H256 {
 /// Blake2b Hash Function
 #[inline]
 pub fn blake256(data: &[u8]) -> H256 {
+    H256::blake_of(data)
+}
+
+/// Double Blake2b hash `blake256(blake256(data))`, the blake analogue of
+/// Bitcoin's hash256 as used for block and transaction ids.
+#[inline]
+pub fn blake256d(data: &[u8]) -> H256 {
+    blake256(blake256(data).as_bytes())
+}
+
+/// Blake2b Hash Function over a batch of inputs, hashing several at once
+/// across SIMD lanes where the platform supports it.
+pub fn blake256_many(inputs: &[&[u8]]) -> Vec<H256> {
     let mut params = Params::new();
     params.hash_length(32);
-    let mut result = [0u8; 32];
-    result.clone_from_slice(&params.hash(data).as_bytes());
-    H256::from(result)
+    let mut jobs: Vec<HashManyJob> = inputs
+        .iter()
+        .map(|input| HashManyJob::new(&params, input))
+        .collect();
+    hash_many(jobs.iter_mut());
+    jobs.iter()
+        .map(|job| H256::from_slice(job.to_hash().as_bytes()))
+        .collect()
 }
 
 /// Blake2b Hash Function
@@ -114,3 +136,24 @@ impl BlakeHasher {
         self
     }
 }
+
+#[test]
+fn test_blake256_many() {
+    let inputs: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; i as usize * 13]).collect();
+    let refs: Vec<&[u8]> = inputs.iter().map(|v| &v[..]).collect();
+
+    let hashes = blake256_many(&refs);
+    assert_eq!(hashes.len(), inputs.len());
+    for (input, hash) in inputs.iter().zip(hashes.iter()) {
+        assert_eq!(*hash, blake256(input));
+    }
+    assert!(blake256_many(&[]).is_empty());
+}
+
+#[test]
+fn test_blake256d() {
+    assert_eq!(blake256d(b"mohan"), blake256d(b"mohan"));
+    assert_eq!(blake256d(b"mohan"), blake256(blake256(b"mohan").as_bytes()));
+    assert_ne!(blake256d(b"mohan"), blake256(b"mohan"));
+    assert_ne!(blake256d(b"mohan"), blake256d(b"mohan!"));
+}
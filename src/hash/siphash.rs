@@ -0,0 +1,299 @@
+// Copyright 2021 Stichting Organism
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SipHash-2-4, a keyed hash for maps whose keys come from untrusted input
+
+use super::H256;
+use byteorder::{ByteOrder, LittleEndian};
+use std::hash::{BuildHasher, Hasher};
+
+/// A 128bit SipHash key. Passing one of these instead of two bare `u64`s
+/// keeps the halves from being swapped or mixed up with other parameters.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SipKey {
+    /// First half of the key
+    pub k0: u64,
+    /// Second half of the key
+    pub k1: u64,
+}
+
+impl SipKey {
+    /// Key from its two halves
+    pub fn new(k0: u64, k1: u64) -> SipKey {
+        SipKey { k0, k1 }
+    }
+
+    /// Key from 16 bytes: `k0` is bytes `0..8` and `k1` bytes `8..16`, both
+    /// read little-endian, as in the SipHash reference code.
+    pub fn from_bytes(bytes: &[u8; 16]) -> SipKey {
+        SipKey {
+            k0: LittleEndian::read_u64(&bytes[..8]),
+            k1: LittleEndian::read_u64(&bytes[8..]),
+        }
+    }
+
+    /// Key from the first 16 bytes of an `H256`, as in `from_bytes`. The last
+    /// 16 bytes are unused.
+    pub fn from_h256(key: &H256) -> SipKey {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&key.as_bytes()[..16]);
+        SipKey::from_bytes(&bytes)
+    }
+
+    /// A fresh key drawn from `rng`
+    pub fn random<R: rand_core::RngCore + rand_core::CryptoRng>(rng: &mut R) -> SipKey {
+        SipKey::new(rng.next_u64(), rng.next_u64())
+    }
+}
+
+/// Streaming SipHash-2-4 with a 128bit key given as two `u64`s
+#[derive(Clone, Debug)]
+pub struct SipHasher24 {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    /// Bytes not yet compressed, little-endian
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+macro_rules! sipround {
+    ($v0:expr, $v1:expr, $v2:expr, $v3:expr) => {
+        $v0 = $v0.wrapping_add($v1);
+        $v1 = $v1.rotate_left(13);
+        $v1 ^= $v0;
+        $v0 = $v0.rotate_left(32);
+        $v2 = $v2.wrapping_add($v3);
+        $v3 = $v3.rotate_left(16);
+        $v3 ^= $v2;
+        $v0 = $v0.wrapping_add($v3);
+        $v3 = $v3.rotate_left(21);
+        $v3 ^= $v0;
+        $v2 = $v2.wrapping_add($v1);
+        $v1 = $v1.rotate_left(17);
+        $v1 ^= $v2;
+        $v2 = $v2.rotate_left(32);
+    };
+}
+
+impl SipHasher24 {
+    /// Create a hasher keyed with `k0` and `k1`
+    pub fn new_with_keys(k0: u64, k1: u64) -> SipHasher24 {
+        SipHasher24 {
+            v0: k0 ^ 0x736f6d6570736575,
+            v1: k1 ^ 0x646f72616e646f6d,
+            v2: k0 ^ 0x6c7967656e657261,
+            v3: k1 ^ 0x7465646279746573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    /// Create a hasher keyed with `key`
+    pub fn new(key: &SipKey) -> SipHasher24 {
+        SipHasher24::new_with_keys(key.k0, key.k1)
+    }
+
+    /// Hash `data` in one go
+    pub fn hash_to_u64(key: &SipKey, data: &[u8]) -> u64 {
+        let mut hasher = SipHasher24::new(key);
+        hasher.write(data);
+        hasher.finish()
+    }
+
+    /// Hash `data` keyed by an `H256`, see `SipKey::from_h256`
+    pub fn hash_to_u64_with_h256_key(key: &H256, data: &[u8]) -> u64 {
+        SipHasher24::hash_to_u64(&SipKey::from_h256(key), data)
+    }
+
+    #[inline]
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        sipround!(self.v0, self.v1, self.v2, self.v3);
+        sipround!(self.v0, self.v1, self.v2, self.v3);
+        self.v0 ^= m;
+    }
+}
+
+impl Hasher for SipHasher24 {
+    fn write(&mut self, msg: &[u8]) {
+        self.length += msg.len();
+        for &byte in msg {
+            self.tail |= (byte as u64) << (8 * self.ntail);
+            self.ntail += 1;
+            if self.ntail == 8 {
+                let m = self.tail;
+                self.compress(m);
+                self.tail = 0;
+                self.ntail = 0;
+            }
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        let (mut v0, mut v1, mut v2, mut v3) = (self.v0, self.v1, self.v2, self.v3);
+        let b = ((self.length as u64 & 0xff) << 56) | self.tail;
+
+        v3 ^= b;
+        sipround!(v0, v1, v2, v3);
+        sipround!(v0, v1, v2, v3);
+        v0 ^= b;
+
+        v2 ^= 0xff;
+        sipround!(v0, v1, v2, v3);
+        sipround!(v0, v1, v2, v3);
+        sipround!(v0, v1, v2, v3);
+        sipround!(v0, v1, v2, v3);
+
+        v0 ^ v1 ^ v2 ^ v3
+    }
+}
+
+/// A `BuildHasher` handing out `SipHasher24`s under one key, so that
+/// `HashMap<K, V, SipBuildHasher>` hashes its keys with a secret key an
+/// attacker cannot aim collisions at.
+#[derive(Clone, Debug)]
+pub struct SipBuildHasher {
+    key: SipKey,
+}
+
+impl SipBuildHasher {
+    /// Use an explicit key
+    pub fn new_with_keys(key: SipKey) -> SipBuildHasher {
+        SipBuildHasher { key }
+    }
+
+    /// Use a fresh random key from `mohan_rand`
+    #[cfg(all(feature = "rand_os", feature = "rand"))]
+    pub fn random() -> SipBuildHasher {
+        SipBuildHasher::new_with_keys(SipKey::random(&mut crate::mohan_rand()))
+    }
+}
+
+/// Randomly keyed, like std's `RandomState`
+#[cfg(all(feature = "rand_os", feature = "rand"))]
+impl Default for SipBuildHasher {
+    fn default() -> SipBuildHasher {
+        SipBuildHasher::random()
+    }
+}
+
+impl BuildHasher for SipBuildHasher {
+    type Hasher = SipHasher24;
+
+    fn build_hasher(&self) -> SipHasher24 {
+        SipHasher24::new(&self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SipBuildHasher, SipHasher24, SipKey, H256};
+    use std::collections::HashMap;
+    use std::hash::{BuildHasher, Hasher};
+
+    const K0: u64 = 0x0706050403020100;
+    const K1: u64 = 0x0f0e0d0c0b0a0908;
+    const KEY: SipKey = SipKey { k0: K0, k1: K1 };
+
+    #[test]
+    fn test_reference_vectors() {
+        // Key 00..0f and message 00..len-1, from the SipHash paper
+        assert_eq!(SipHasher24::hash_to_u64(&KEY, &[]), 0x726fdb47dd0e0e31);
+        assert_eq!(SipHasher24::hash_to_u64(&KEY, &[0]), 0x74f839c593dc67fd);
+    }
+
+    #[test]
+    fn test_sip_key() {
+        // Bytes 00..0f are the reference key, read as two little-endian words
+        let mut bytes = [0u8; 16];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let key = SipKey::from_bytes(&bytes);
+        assert_eq!(key, KEY);
+
+        let data = b"some data";
+        let mut raw = SipHasher24::new_with_keys(K0, K1);
+        raw.write(data);
+        assert_eq!(SipHasher24::hash_to_u64(&key, data), raw.finish());
+
+        let swapped = SipKey::new(K1, K0);
+        assert_ne!(
+            SipHasher24::hash_to_u64(&swapped, data),
+            SipHasher24::hash_to_u64(&key, data)
+        );
+    }
+
+    #[test]
+    fn test_h256_key() {
+        // Bytes 00..0f give the reference key K0, K1
+        let mut bytes = [0xffu8; 32];
+        for (i, b) in bytes[..16].iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let key = H256(bytes);
+        assert_eq!(SipKey::from_h256(&key), KEY);
+        assert_eq!(
+            SipHasher24::hash_to_u64_with_h256_key(&key, &[]),
+            0x726fdb47dd0e0e31
+        );
+        assert_eq!(
+            SipHasher24::hash_to_u64_with_h256_key(&key, b"data"),
+            SipHasher24::hash_to_u64(&KEY, b"data")
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_matches_std() {
+        // std's deprecated SipHasher is SipHash-2-4 as well
+        let data: Vec<u8> = (0..100).collect();
+        for len in 0..data.len() {
+            let mut std_hasher = std::hash::SipHasher::new_with_keys(K0, K1);
+            std_hasher.write(&data[..len]);
+            assert_eq!(
+                SipHasher24::hash_to_u64(&KEY, &data[..len]),
+                std_hasher.finish()
+            );
+        }
+
+        // Split writes give the same result as one write
+        let mut hasher = SipHasher24::new_with_keys(K0, K1);
+        hasher.write(&data[..3]);
+        hasher.write(&data[3..50]);
+        hasher.write(&data[50..]);
+        assert_eq!(hasher.finish(), SipHasher24::hash_to_u64(&KEY, &data));
+    }
+
+    #[cfg(all(feature = "rand_os", feature = "rand"))]
+    #[test]
+    fn test_build_hasher_map() {
+        let mut map = HashMap::with_hasher(SipBuildHasher::random());
+        for i in 0..100u64 {
+            map.insert(i, i * 2);
+        }
+        for i in 0..100u64 {
+            assert_eq!(map.get(&i), Some(&(i * 2)));
+        }
+
+        let a = SipBuildHasher::new_with_keys(SipKey::new(1, 2));
+        let b = SipBuildHasher::new_with_keys(SipKey::new(3, 4));
+        assert_eq!(a.hash_one("key"), a.hash_one("key"));
+        assert_ne!(a.hash_one("key"), b.hash_one("key"));
+    }
+}
@@ -16,16 +16,35 @@ use crate::hex;
 use crate::ser::{
     self, AsFixedBytes, FixedLength, ProtocolVersion, Readable, Reader, Writeable, Writer,
 };
+use crate::tools::RistrettoBoth;
 use byteorder::{BigEndian, ByteOrder};
 use serde::{Deserialize, Serialize};
 use std::cmp::min;
-use std::convert::AsRef;
+use std::convert::{AsRef, TryFrom};
 use std::ops::Add;
 use std::{fmt, ops};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
 
 
 fixed_hash::construct_fixed_hash! {
     /// My 256 bit hash type.
+    ///
+    /// `Hash` feeds the raw bytes to whatever `Hasher` the map uses. When
+    /// keys come from untrusted peers use a map keyed with a secret, such as
+    /// one built on `SipBuildHasher`, rather than a fixed hasher, or an
+    /// attacker can pick hashes that all land in the same bucket.
+    ///
+    /// ```
+    /// # #[cfg(all(feature = "rand_os", feature = "rand"))] {
+    /// use mohan::hash::{SipBuildHasher, H256};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut seen: HashMap<H256, u64, SipBuildHasher> =
+    ///     HashMap::with_hasher(SipBuildHasher::random());
+    /// seen.insert(H256::zero(), 1);
+    /// assert_eq!(seen.get(&H256::zero()), Some(&1));
+    /// # }
+    /// ```
     #[derive(Serialize, Deserialize)]
     pub struct H256(32);
 }
@@ -34,7 +53,14 @@ fixed_hash::construct_fixed_hash! {
 impl DefaultHashable for H256 {}
 
 impl H256 {
-  
+    /// Blake2b 256bit hash of `data`, written straight into the result.
+    #[inline]
+    pub fn blake_of(data: &[u8]) -> H256 {
+        let mut params = crate::blake2::Params::new();
+        params.hash_length(32);
+        H256::from_slice(params.hash(data).as_bytes())
+    }
+
     pub fn hash_with<T: Writeable>(&self, other: T) -> H256 {
         let mut hasher = HashWriter::default();
         ser::Writeable::write(self, &mut hasher).unwrap();
@@ -46,6 +72,11 @@ impl H256 {
 
     /// Builds a Hash from a byte vector. If the vector is too short, it will be
     /// completed by zeroes. If it's too long, it will be truncated.
+    ///
+    /// # Warning
+    ///
+    /// This never fails, so passing data of the wrong length goes unnoticed.
+    /// Use `try_from_slice` unless padding or truncation is intended.
     pub fn from_vec(v: &[u8]) -> H256 {
         let mut h = [0; H256::LEN];
         let copy_size = min(v.len(), H256::LEN);
@@ -53,6 +84,17 @@ impl H256 {
         H256(h)
     }
 
+    /// Builds a Hash from a slice that must be exactly 32 bytes long.
+    pub fn try_from_slice(v: &[u8]) -> Result<H256, ser::Error> {
+        if v.len() != H256::LEN {
+            return Err(ser::Error::InvalidLength {
+                expected: H256::LEN,
+                received: v.len(),
+            });
+        }
+        Ok(H256::from_slice(v))
+    }
+
     /// Converts the hash to a byte vector
     pub fn to_vec(&self) -> Vec<u8> {
         self.0.to_vec()
@@ -63,10 +105,17 @@ impl H256 {
         hex::to_hex(&self.to_vec())
     }
 
+    /// All 64 nibbles with a `0x` prefix, unlike `Display` which abbreviates
+    /// to `0x1234…cdef`. `Display` comes from `fixed_hash` and ignores the
+    /// `{:#}` flag; `{:#x}` and `{:?}` print the same as this.
+    pub fn to_full_hex(&self) -> String {
+        format!("{:#x}", self)
+    }
+
     /// Convert hex string back to hash.
     pub fn from_hex(hex: &str) -> Result<H256, ser::Error> {
         let bytes = hex::from_hex(hex.to_string())
-            .map_err(|_| ser::Error::HexError(format!("failed to decode {}", hex)))?;
+            .map_err(|e| ser::Error::HexError(format!("failed to decode {}: {}", hex, e)))?;
         Ok(H256::from_vec(&bytes))
     }
 
@@ -75,15 +124,96 @@ impl H256 {
         BigEndian::read_u64(&self.0)
     }
 
+    /// The hash as four big-endian words, most significant first: word `i`
+    /// holds bytes `8 * i..8 * i + 8`. This is the reverse of the word order
+    /// in `to_uint().0`.
+    pub fn to_u64_array(&self) -> [u64; 4] {
+        let mut words = [0u64; 4];
+        BigEndian::read_u64_into(&self.0, &mut words);
+        words
+    }
+
+    /// Inverse of `to_u64_array`
+    pub fn from_u64_array(words: [u64; 4]) -> H256 {
+        let mut ret = [0u8; 32];
+        BigEndian::write_u64_into(&words, &mut ret);
+        H256(ret)
+    }
+
     /// Convert Hash into a Scalar
+    ///
+    /// # Warning
+    ///
+    /// The bytes are taken as is and NOT reduced modulo the group order, so
+    /// for an arbitrary hash the result is usually a non canonical scalar.
+    /// Only use this when the value is already known to be canonical, and
+    /// prefer `to_scalar_reduced` otherwise.
     pub fn into_scalar(&self) -> crate::dalek::scalar::Scalar {
         crate::dalek::scalar::Scalar::from_bits(self.0)
     }
 
+    /// Convert Hash into a canonical Scalar by reducing it modulo the group
+    /// order.
+    pub fn to_scalar_reduced(&self) -> crate::dalek::scalar::Scalar {
+        crate::dalek::scalar::Scalar::from_bytes_mod_order(self.0)
+    }
+
+    /// Reduce to a Scalar as `to_scalar_reduced` does, then negate it in
+    /// constant time when `choice` is set. dalek implements its own fork of
+    /// `subtle`, so the selection is done here byte by byte with ours.
+    pub fn conditional_negate_as_scalar(&self, choice: Choice) -> crate::dalek::scalar::Scalar {
+        let s = self.to_scalar_reduced();
+        let neg = (-s).to_bytes();
+        let mut bytes = s.to_bytes();
+        for (b, n) in bytes.iter_mut().zip(neg.iter()) {
+            b.conditional_assign(n, choice);
+        }
+        crate::dalek::scalar::Scalar::from_bits(bytes)
+    }
+
+    /// Store a point's compressed encoding as a hash shaped value
+    pub fn from_ristretto(p: &RistrettoBoth) -> H256 {
+        H256(p.to_bytes())
+    }
+
+    /// Decompress back into a point, `None` if the bytes are not a valid
+    /// Ristretto encoding
+    pub fn to_ristretto(&self) -> Option<RistrettoBoth> {
+        RistrettoBoth::from_bytes(&self.0)
+    }
+
     ///Flip into u256
     pub fn to_uint(&self) -> crate::U256 {
         crate::U256::from(self.0)
     }
+
+    /// Reverse the order of all 256 bits, so bit `i` moves to `255 - i`.
+    /// Agrees with `U256::reverse_bits` through `to_uint`.
+    pub fn reverse_bits(&self) -> H256 {
+        let mut ret = [0u8; 32];
+        for (i, byte) in self.0.iter().rev().enumerate() {
+            ret[i] = byte.reverse_bits();
+        }
+        H256(ret)
+    }
+
+    /// Does the hash, read as a big-endian number, not exceed `target`?
+    /// This is the proof of work check.
+    pub fn meets_target(&self, target: &crate::U256) -> bool {
+        self.to_uint() <= *target
+    }
+
+    /// Kademlia style XOR distance between two hashes. Comparing distances
+    /// with `<` orders them as 256bit big-endian numbers.
+    pub fn xor_distance(&self, other: &H256) -> H256 {
+        *self ^ *other
+    }
+
+    /// Constant time check for zero, for use on secret derived values
+    pub fn ct_is_zero(&self) -> Choice {
+        let acc = self.0.iter().fold(0u8, |acc, byte| acc | byte);
+        acc.ct_eq(&0)
+    }
 }
 
 
@@ -93,6 +223,88 @@ impl FixedLength for H256 {
 }
 
 
+/// A trait for types that can be addressed as a fixed length array of bits
+pub trait BitArray {
+    /// Is bit set?
+    fn bit(&self, idx: usize) -> bool;
+
+    /// Returns a copy keeping only the bits in `start..end`, all others cleared
+    fn bit_slice(&self, start: usize, end: usize) -> Self;
+
+    /// Returns a copy keeping only bits `0..n`, all others cleared. For
+    /// `H256` those are the `n` most significant bits, not the low ones.
+    fn mask(&self, n: usize) -> Self;
+
+    /// Number of leading zero bits, counting from bit 0
+    fn leading_zeros(&self) -> u32;
+
+    /// Number of trailing zero bits, counting back from the last bit
+    fn trailing_zeros(&self) -> u32;
+
+    /// Create all-zeros value
+    fn zero() -> Self;
+
+    /// Create value representing one
+    fn one() -> Self;
+}
+
+/// Bits of a `H256` are indexed big-endian: bit 0 is the most significant
+/// bit of the first byte and bit 255 the least significant bit of the last
+/// byte. Walking `0..256` therefore visits the hash in the order its bytes
+/// are laid out, which is what a binary trie path expects. `mask(n)` keeps
+/// the first `n` bits (a path prefix). `leading_zeros` counts from bit 0 and
+/// `trailing_zeros` from bit 255 backwards, matching the same methods on
+/// `to_uint()`, which is what a proof of work check compares.
+impl BitArray for H256 {
+    #[inline]
+    fn bit(&self, idx: usize) -> bool {
+        self.0[idx / 8] & (0x80 >> (idx % 8)) != 0
+    }
+
+    fn bit_slice(&self, start: usize, end: usize) -> H256 {
+        let mut ret = H256::zero();
+        for idx in start..min(end, 256) {
+            if self.bit(idx) {
+                ret.0[idx / 8] |= 0x80 >> (idx % 8);
+            }
+        }
+        ret
+    }
+
+    #[inline]
+    fn mask(&self, n: usize) -> H256 {
+        self.bit_slice(0, n)
+    }
+
+    fn leading_zeros(&self) -> u32 {
+        for (i, byte) in self.0.iter().enumerate() {
+            if *byte != 0 {
+                return i as u32 * 8 + byte.leading_zeros();
+            }
+        }
+        256
+    }
+
+    fn trailing_zeros(&self) -> u32 {
+        for (i, byte) in self.0.iter().rev().enumerate() {
+            if *byte != 0 {
+                return i as u32 * 8 + byte.trailing_zeros();
+            }
+        }
+        256
+    }
+
+    #[inline]
+    fn zero() -> H256 {
+        H256::zero()
+    }
+
+    #[inline]
+    fn one() -> H256 {
+        H256::from_low_u64_be(1)
+    }
+}
+
 /// A trait for types that have a canonical hash
 pub trait Hashed {
     /// Obtain the hash of the object at 256bits
@@ -114,6 +326,19 @@ impl Writeable for H256 {
     }
 }
 
+impl TryFrom<&[u8]> for H256 {
+    type Error = ser::Error;
+
+    /// Same as `H256::try_from_slice`, any length other than 32 is an error.
+    fn try_from(v: &[u8]) -> Result<H256, ser::Error> {
+        H256::try_from_slice(v)
+    }
+}
+
+/// `a + b` is NOT arithmetic: it is the hash of `a` followed by `b`, see
+/// `hash_with`. It is neither commutative nor associative. The bitwise
+/// `^`, `&` and `|` operators, which `fixed_hash` provides, do work byte by
+/// byte; use `to_uint` for numeric addition.
 impl Add for H256 {
     type Output = H256;
     fn add(self, other: H256) -> H256 {
@@ -121,8 +346,125 @@ impl Add for H256 {
     }
 }
 
+fixed_hash::construct_fixed_hash! {
+    /// My 512 bit hash type.
+    pub struct H512(64);
+}
+
+impl H512 {
+    /// Split into `(hi, lo)` halves. `hi` holds bytes `0..32` and `lo`
+    /// bytes `32..64`, so reading both big-endian keeps the high half first.
+    pub fn split(&self) -> (H256, H256) {
+        (
+            H256::from_slice(&self.0[..32]),
+            H256::from_slice(&self.0[32..]),
+        )
+    }
+
+    /// Join two halves, the inverse of `split`: `hi` becomes bytes `0..32`
+    /// and `lo` bytes `32..64`.
+    pub fn from_halves(hi: H256, lo: H256) -> H512 {
+        let mut ret = [0u8; 64];
+        ret[..32].copy_from_slice(hi.as_bytes());
+        ret[32..].copy_from_slice(lo.as_bytes());
+        H512(ret)
+    }
+}
+
+impl FixedLength for H512 {
+    /// Size of a hash in bytes.
+    const LEN: usize = 64;
+}
+
+impl Readable for H512 {
+    fn read(reader: &mut dyn Reader) -> Result<H512, ser::Error> {
+        let v = reader.read_fixed_bytes(64)?;
+        let mut a = [0; 64];
+        a.copy_from_slice(&v[..]);
+        Ok(H512(a))
+    }
+}
+
+impl Writeable for H512 {
+    fn write<W: Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+        writer.write_fixed_bytes(&self.0)
+    }
+}
+
+// serde only implements arrays up to 32 elements, so H512 can't derive like
+// H256 does. These impls give it the same shape the derive gives H256: a
+// newtype struct around a tuple of its bytes.
+impl Serialize for H512 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Bytes<'a>(&'a [u8; 64]);
+
+        impl Serialize for Bytes<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::SerializeTuple;
+                let mut tup = serializer.serialize_tuple(64)?;
+                for byte in self.0.iter() {
+                    tup.serialize_element(byte)?;
+                }
+                tup.end()
+            }
+        }
+
+        serializer.serialize_newtype_struct("H512", &Bytes(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for H512 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<H512, D::Error> {
+        use serde::de::{self, SeqAccess, Visitor};
+
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = H512;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an array of 64 bytes")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<H512, A::Error> {
+                let mut ret = [0u8; 64];
+                for (i, byte) in ret.iter_mut().enumerate() {
+                    *byte = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+                }
+                Ok(H512(ret))
+            }
+        }
+
+        struct H512Visitor;
+
+        impl<'de> Visitor<'de> for H512Visitor {
+            type Value = H512;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("tuple struct H512")
+            }
+
+            fn visit_newtype_struct<D: serde::Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<H512, D::Error> {
+                deserializer.deserialize_tuple(64, BytesVisitor)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct("H512", H512Visitor)
+    }
+}
+
 
 /// Serializer that outputs a hash of the serialized object
+///
+/// Bytes are fed to blake256 exactly as `BinWriter` would emit them, so for
+/// any `Writeable` that does not check `serialization_mode` the hash is
+/// `blake256(&ser_vec(x))`. Types that leave data out in `Hash` mode hash
+/// only what they write there.
 pub struct HashWriter {
     state: crate::blake2::State,
 }
@@ -169,7 +511,8 @@ impl ser::Writer for HashWriter {
 }
 
 /// Implementing this trait enables the default
-/// hash implementation
+/// hash implementation, the blake256 of what `write` emits to a
+/// `HashWriter`
 pub trait DefaultHashable: Writeable {}
 impl<D: DefaultHashable> Hashed for D {
     fn hash(&self) -> H256 {
@@ -182,12 +525,22 @@ impl<D: DefaultHashable> Hashed for D {
 }
 
 impl DefaultHashable for Vec<u8> {}
+impl DefaultHashable for u8 {}
+impl DefaultHashable for u16 {}
+impl DefaultHashable for u32 {}
+impl DefaultHashable for i32 {}
 impl DefaultHashable for u64 {}
+impl DefaultHashable for i64 {}
+
+impl<A: Writeable, B: Writeable> DefaultHashable for (A, B) {}
+impl<A: Writeable, B: Writeable, C: Writeable> DefaultHashable for (A, B, C) {}
+impl<A: Writeable, B: Writeable, C: Writeable, D: Writeable> DefaultHashable for (A, B, C, D) {}
 
 
 #[cfg(test)]
 mod tests {
-    use super::H256;
+    use super::{BitArray, Hashed, H256, H512};
+    use crate::ser;
     use std::str::FromStr;
 
     #[test]
@@ -219,4 +572,360 @@ mod tests {
 
         assert_eq!(rawzero, strzero);
     }
+
+    #[test]
+    fn test_try_from_slice() {
+        let bytes: Vec<u8> = (0..32).collect();
+        assert_eq!(
+            H256::try_from_slice(&bytes).unwrap(),
+            H256::from_vec(&bytes)
+        );
+
+        assert_eq!(
+            H256::try_from_slice(&bytes[..4]),
+            Err(ser::Error::InvalidLength {
+                expected: 32,
+                received: 4
+            })
+        );
+        assert!(H256::try_from_slice(&[]).is_err());
+
+        let mut long = bytes.clone();
+        long.extend_from_slice(&bytes);
+        assert_eq!(
+            H256::try_from_slice(&long),
+            Err(ser::Error::InvalidLength {
+                expected: 32,
+                received: 64
+            })
+        );
+
+        // Off by one either way, such as a blake output of the wrong size
+        assert!(H256::try_from_slice(&bytes[..31]).is_err());
+        assert!(H256::try_from_slice(&long[..33]).is_err());
+        assert!(H256::try_from_slice(&long[1..33]).is_ok());
+    }
+
+    #[test]
+    fn test_h256_keyed_map() {
+        use crate::hash::{SipBuildHasher, SipKey};
+        use std::collections::HashMap;
+
+        let keys: Vec<H256> = (0..1000u64).map(|i| i.hash()).collect();
+        let key = SipKey::new(0x0706050403020100, 0x0f0e0d0c0b0a0908);
+        let hasher = SipBuildHasher::new_with_keys(key);
+        let mut map: HashMap<H256, u64, SipBuildHasher> = HashMap::with_hasher(hasher);
+        for (i, k) in keys.iter().enumerate() {
+            map.insert(*k, i as u64);
+        }
+
+        assert_eq!(map.len(), keys.len());
+        for (i, k) in keys.iter().enumerate() {
+            assert_eq!(map.get(k), Some(&(i as u64)));
+        }
+        assert_eq!(map.get(&H256::zero()), None);
+    }
+
+    #[test]
+    fn test_blake_of() {
+        assert_eq!(
+            H256::blake_of(b""),
+            H256::from_hex("0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8")
+                .unwrap()
+        );
+        assert_eq!(
+            H256::blake_of(b"abc"),
+            H256::from_hex("bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319")
+                .unwrap()
+        );
+        let data = vec![7u8; 1000];
+        assert_eq!(H256::blake_of(&data), crate::hash::blake256(&data));
+    }
+
+    #[test]
+    fn test_hash_matches_full_serialization() {
+        use crate::hash::blake256;
+        use crate::ser::{ser_vec, ProtocolVersion};
+
+        fn check<T: Hashed + ser::Writeable>(x: T) {
+            let bytes = ser_vec(&x, ProtocolVersion::local()).unwrap();
+            assert_eq!(x.hash(), blake256(&bytes));
+        }
+
+        check(0xdead_beefu64);
+        check(-7i32);
+        check(H256::repeat_byte(0x42));
+        check(b"some bytes".to_vec());
+        check((1u8, H256::zero(), vec![3u8; 300]));
+        check((H256::repeat_byte(1), 2u16, 3i64));
+    }
+
+    #[test]
+    fn test_u64_array() {
+        let h = crate::hash::blake256(b"words");
+        let words = h.to_u64_array();
+        assert_eq!(H256::from_u64_array(words), h);
+        assert_eq!(words[0], h.to_u64());
+
+        let mut uint_words = h.to_uint().0;
+        uint_words.reverse();
+        assert_eq!(words, uint_words);
+
+        assert_eq!(H256::from_low_u64_be(5).to_u64_array(), [0, 0, 0, 5]);
+    }
+
+    #[test]
+    fn test_full_hex() {
+        let h = crate::hash::blake256(b"abc");
+        let full = "0xbddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319";
+
+        assert_eq!(format!("{}", h), "0xbddd…2319");
+        assert_eq!(h.to_full_hex(), full);
+        assert_eq!(format!("{:#x}", h), full);
+        assert_eq!(format!("{:?}", h), full);
+        assert_eq!(H256::from_hex(&h.to_full_hex()).unwrap(), h);
+    }
+
+    #[test]
+    fn test_bit_ops_and_xor_distance() {
+        let x = crate::hash::blake256(b"x");
+        let y = crate::hash::blake256(b"y");
+
+        assert_eq!(x ^ x, H256::zero());
+        assert_eq!(x ^ H256::zero(), x);
+        assert_eq!(x & H256::zero(), H256::zero());
+        assert_eq!(x | H256::zero(), x);
+        assert_eq!(x & H256::repeat_byte(0xff), x);
+        assert_eq!((x ^ y) ^ y, x);
+        assert_ne!(x + y, x ^ y);
+
+        assert_eq!(x.xor_distance(&x), H256::zero());
+        assert_eq!(x.xor_distance(&y), y.xor_distance(&x));
+
+        // Flipping a lower bit moves less far than flipping a higher one
+        let near = x ^ H256::from_low_u64_be(1);
+        let far = x ^ H256::from_vec(&[0x80]);
+        assert!(x.xor_distance(&near) < x.xor_distance(&far));
+        assert_eq!(x.xor_distance(&far).to_uint(), crate::U256::one() << 255);
+    }
+
+    #[test]
+    fn test_zeros_and_target() {
+        let h =
+            H256::from_hex("0x0000000000000000000f8b3c1a2e4d7f99887766554433221100aabbccddeeff")
+                .unwrap();
+        assert_eq!(h.leading_zeros(), 76);
+        assert_eq!(h.leading_zeros(), h.to_uint().leading_zeros());
+        assert_eq!(h.trailing_zeros(), 0);
+        assert_eq!(H256::from_low_u64_be(0x100).trailing_zeros(), 8);
+        assert_eq!(H256::zero().leading_zeros(), 256);
+        assert_eq!(H256::zero().trailing_zeros(), 256);
+        assert_eq!(H256::repeat_byte(0xff).leading_zeros(), 0);
+
+        let target = h.to_uint();
+        assert!(h.meets_target(&target));
+        assert!(h.meets_target(&(target + 1)));
+        assert!(!h.meets_target(&(target - 1)));
+        assert!(H256::zero().meets_target(&crate::U256::zero()));
+    }
+
+    #[test]
+    fn test_try_from() {
+        use std::convert::TryFrom;
+
+        let bytes: Vec<u8> = (0..32).collect();
+        let h = H256::try_from(&bytes[..]).unwrap();
+        assert_eq!(h.as_bytes(), &bytes[..]);
+
+        assert_eq!(
+            H256::try_from(&bytes[..31]),
+            Err(ser::Error::InvalidLength {
+                expected: 32,
+                received: 31
+            })
+        );
+        let long = [0u8; 33];
+        assert_eq!(
+            H256::try_from(&long[..]),
+            Err(ser::Error::InvalidLength {
+                expected: 32,
+                received: 33
+            })
+        );
+    }
+
+    #[test]
+    fn test_h512_halves() {
+        let hi = crate::hash::blake256(b"hi");
+        let lo = crate::hash::blake256(b"lo");
+        let x = H512::from_halves(hi, lo);
+        assert_eq!(&x.as_bytes()[..32], hi.as_bytes());
+        assert_eq!(x.split(), (hi, lo));
+        assert_eq!(H512::from_halves(x.split().0, x.split().1), x);
+
+        let y = H512::random();
+        let (hi, lo) = y.split();
+        assert_eq!(H512::from_halves(hi, lo), y);
+    }
+
+    #[test]
+    fn test_h512_ser_round_trip() {
+        let x = H512::from_halves(crate::hash::blake256(b"hi"), crate::hash::blake256(b"lo"));
+
+        let data = ser::ser_vec(&x, ser::ProtocolVersion::local()).unwrap();
+        assert_eq!(data, x.as_bytes());
+        let back: H512 = ser::deserialize_default(&mut &data[..]).unwrap();
+        assert_eq!(back, x);
+
+        let short: Result<H512, ser::Error> = ser::deserialize_default(&mut &data[..63]);
+        assert!(short.is_err());
+    }
+
+    #[test]
+    fn test_h512_serde_round_trip() {
+        let x = H512::from_halves(crate::hash::blake256(b"hi"), crate::hash::blake256(b"lo"));
+
+        // Same shape as the derived H256 impls: the bare bytes in bincode
+        let data = bincode::serialize(&x).unwrap();
+        assert_eq!(data, x.as_bytes());
+        assert_eq!(bincode::deserialize::<H512>(&data).unwrap(), x);
+        assert!(bincode::deserialize::<H512>(&data[..63]).is_err());
+
+        let h = crate::hash::blake256(b"hi");
+        assert_eq!(bincode::serialize(&h).unwrap(), h.as_bytes());
+
+        // And inside a derived struct
+        use serde::{Deserialize, Serialize};
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Pair {
+            a: H256,
+            b: H512,
+        }
+        let pair = Pair { a: h, b: x };
+        let data = bincode::serialize(&pair).unwrap();
+        assert_eq!(data.len(), 96);
+        assert_eq!(bincode::deserialize::<Pair>(&data).unwrap(), pair);
+    }
+
+    #[test]
+    fn test_h256_reverse_bits() {
+        let h = crate::hash::blake256(b"reverse");
+        assert_eq!(h.reverse_bits().reverse_bits(), h);
+        assert_eq!(h.reverse_bits().to_uint(), h.to_uint().reverse_bits());
+        for i in 0..256 {
+            assert_eq!(h.bit(i), h.reverse_bits().bit(255 - i));
+        }
+    }
+
+    #[test]
+    fn test_tuple_hash() {
+        let h = H256::from_low_u64_be(7);
+        let a = (42u64, h).hash();
+        assert_eq!(a, (42u64, h).hash());
+        assert_ne!(a, (43u64, h).hash());
+        assert_ne!(a, (42u64, H256::zero()).hash());
+
+        let mut concat = 42u64.to_le_bytes().to_vec();
+        concat.extend_from_slice(h.as_bytes());
+        assert_eq!(a, crate::hash::blake256(&concat));
+
+        assert_ne!(1u8.hash(), 1u16.hash());
+        assert_ne!((1u32, 2i64).hash(), (1u32, 2i64, 3u8).hash());
+    }
+
+    #[test]
+    fn test_to_scalar_reduced() {
+        let h = H256::repeat_byte(0xff);
+        assert!(!h.into_scalar().is_canonical());
+        assert!(h.to_scalar_reduced().is_canonical());
+
+        // Already canonical values are left alone
+        let small = H256::from_vec(&[42]);
+        assert_eq!(small.to_scalar_reduced(), small.into_scalar());
+    }
+
+    #[test]
+    fn test_conditional_negate_as_scalar() {
+        let h = H256::repeat_byte(0xff);
+        let s = h.to_scalar_reduced();
+
+        let kept = h.conditional_negate_as_scalar(subtle::Choice::from(0));
+        assert_eq!(kept, s);
+        let negated = h.conditional_negate_as_scalar(subtle::Choice::from(1));
+        assert_eq!(negated, -s);
+        assert!(negated.is_canonical());
+        assert_eq!(kept + negated, crate::dalek::scalar::Scalar::zero());
+    }
+
+    #[cfg(all(feature = "rand_os", feature = "rand"))]
+    #[test]
+    fn test_ristretto_round_trip() {
+        use crate::tools::RistrettoBoth;
+
+        let p = RistrettoBoth::random(&mut crate::mohan_rand());
+        let h = H256::from_ristretto(&p);
+        assert_eq!(h.as_bytes(), p.as_bytes());
+        assert_eq!(h.to_ristretto(), Some(p));
+
+        // Negative field element, not a valid encoding
+        assert_eq!(H256::from_vec(&[1]).to_ristretto(), None);
+        assert_eq!(H256::repeat_byte(0xff).to_ristretto(), None);
+    }
+
+    #[test]
+    fn test_h256_ct_is_zero() {
+        let values = [
+            H256::zero(),
+            H256::from_low_u64_be(1),
+            H256::from_vec(&[0x80]),
+            H256::repeat_byte(0xff),
+        ];
+        for v in values.iter() {
+            assert_eq!(bool::from(v.ct_is_zero()), v.is_zero());
+        }
+        assert!(bool::from(H256::zero().ct_is_zero()));
+    }
+
+    #[test]
+    fn test_h256_bit_array() {
+        let h =
+            H256::from_hex("0x8000000000000000000000000000000000000000000000000000000000000001")
+                .unwrap();
+        assert!(h.bit(0));
+        assert!(!h.bit(1));
+        assert!(!h.bit(254));
+        assert!(h.bit(255));
+        assert_eq!(h.trailing_zeros(), 0);
+
+        let h =
+            H256::from_hex("0x0000000000000000000000000000000000000000000000000000000000000100")
+                .unwrap();
+        assert!(!h.bit(0));
+        assert!(h.bit(247));
+        assert_eq!(h.trailing_zeros(), 8);
+        assert_eq!(h.trailing_zeros(), h.to_uint().trailing_zeros());
+
+        assert_eq!(H256::zero().trailing_zeros(), 256);
+        assert!(<H256 as BitArray>::one().bit(255));
+    }
+
+    #[test]
+    fn test_h256_bit_slice_mask() {
+        let h = H256::repeat_byte(0xff);
+        let masked = h.mask(12);
+        assert_eq!(masked.0[0], 0xff);
+        assert_eq!(masked.0[1], 0xf0);
+        assert!(masked.0[2..].iter().all(|b| *b == 0));
+
+        // Not byte aligned: the top 3 bits survive, the low ones do not
+        let masked = h.mask(3);
+        assert_eq!(masked.0[0], 0xe0);
+        assert!(masked.0[1..].iter().all(|b| *b == 0));
+        assert_eq!(masked.to_uint(), crate::U256::from(7) << 253);
+        assert_eq!(H256::from_low_u64_be(7).mask(3), H256::zero());
+
+        let sliced = h.bit_slice(4, 8);
+        assert_eq!(sliced.0[0], 0x0f);
+        assert!(sliced.0[1..].iter().all(|b| *b == 0));
+    }
 }
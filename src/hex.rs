@@ -17,8 +17,9 @@
 /// to bytes. Given that rustc-serialize is deprecated and serde doesn't
 /// provide easy hex encoding, hex is a bit in limbo right now in Rust-
 /// land. It's simple enough that we can just have our own.
-use std::fmt::Write;
-use std::num;
+use std::fmt::{self, Write};
+use thiserror::Error;
+use zeroize::Zeroizing;
 
 /// Encode the provided bytes into a hex string
 pub fn to_hex(bytes: &[u8]) -> String {
@@ -30,30 +31,113 @@ pub fn to_hex(bytes: &[u8]) -> String {
     s
 }
 
-/// Decode a hex string into bytes.
-pub fn from_hex(hex_str: String) -> Result<Vec<u8>, num::ParseIntError> {
-    if hex_str.len() % 2 == 1 {
-        // TODO: other way to instantiate a ParseIntError?
-        let err = ("QQQ").parse::<u64>();
-        if let Err(e) = err {
-            return Err(e);
+/// Format bytes as an `xxd` style hex dump: each line holds an offset, 16
+/// bytes in groups of two and the printable ASCII characters, with `.`
+/// standing in for the rest.
+pub fn dump(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    dump_to(bytes, &mut s).expect("Unable to write");
+    s
+}
+
+/// Write the hex dump produced by `dump` to `out`.
+pub fn dump_to<W: Write>(bytes: &[u8], out: &mut W) -> fmt::Result {
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        write!(out, "{:08x}:", line * 16)?;
+        for i in 0..16 {
+            if i % 2 == 0 {
+                out.write_char(' ')?;
+            }
+            match chunk.get(i) {
+                Some(byte) => write!(out, "{:02x}", byte)?,
+                None => out.write_str("  ")?,
+            }
+        }
+        out.write_str("  ")?;
+        for &byte in chunk {
+            let c = if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            };
+            out.write_char(c)?;
         }
+        out.write_char('\n')?;
     }
-    let hex_trim = if &hex_str[..2] == "0x" {
-        hex_str[2..].to_owned()
+    Ok(())
+}
+
+/// Errors from decoding a hex string
+#[derive(Copy, Clone, Debug, Eq, Error, PartialEq)]
+pub enum Error {
+    /// The digits do not pair up into whole bytes
+    #[error("odd number of hex digits")]
+    OddLength,
+
+    /// A character that is not a hex digit, `index` is its byte offset in
+    /// the input
+    #[error("invalid hex character {ch:?} at index {index}")]
+    InvalidChar { index: usize, ch: char },
+
+    /// A prefix other than the lower case `0x`, such as `0X` or a bare `x`
+    #[error("invalid hex prefix")]
+    InvalidPrefix,
+}
+
+/// Decode a hex string into bytes.
+pub fn from_hex(hex_str: String) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::with_capacity(hex_str.len() / 2);
+    decode_into(&hex_str, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decode a hex string holding secret material, such as a private key.
+/// The bytes are decoded straight into a buffer that is wiped on drop and
+/// no intermediate copies of the input are made.
+pub fn from_hex_secret(hex_str: &str) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(hex_str.len() / 2));
+    decode_into(hex_str, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decode `hex_str` onto the end of `out`. Surrounding whitespace and an
+/// optional `0x` prefix are skipped.
+fn decode_into(hex_str: &str, out: &mut Vec<u8>) -> Result<(), Error> {
+    let trimmed = hex_str.trim_start();
+    let mut offset = hex_str.len() - trimmed.len();
+    let digits = if let Some(rest) = trimmed.strip_prefix("0x") {
+        offset += 2;
+        rest
+    } else if trimmed.starts_with("0X") || trimmed.starts_with('x') {
+        return Err(Error::InvalidPrefix);
     } else {
-        hex_str.clone()
+        trimmed
     };
-    split_n(&hex_trim.trim()[..], 2)
-        .iter()
-        .map(|b| u8::from_str_radix(b, 16))
-        .collect::<Result<Vec<u8>, _>>()
+    let digits = digits.trim_end();
+
+    if let Some((index, ch)) = digits.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(Error::InvalidChar {
+            index: offset + index,
+            ch,
+        });
+    }
+    // only ascii hex digits are left, so bytes and chars line up
+    if digits.len() % 2 == 1 {
+        return Err(Error::OddLength);
+    }
+
+    for pair in digits.as_bytes().chunks(2) {
+        out.push(hex_val(pair[0]) << 4 | hex_val(pair[1]));
+    }
+    Ok(())
 }
 
-fn split_n(s: &str, n: usize) -> Vec<&str> {
-    (0..(s.len() - n + 1) / 2 + 1)
-        .map(|i| &s[2 * i..2 * i + n])
-        .collect()
+fn hex_val(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        _ => c - b'A' + 10,
+    }
 }
 
 #[cfg(test)]
@@ -79,4 +163,54 @@ mod test {
             vec![0, 0, 0, 255]
         );
     }
+
+    #[test]
+    fn test_from_hex_secret() {
+        assert_eq!(&from_hex_secret("0a0b0c0d").unwrap()[..], &[10, 11, 12, 13]);
+        assert_eq!(&from_hex_secret("0x000000ff").unwrap()[..], &[0, 0, 0, 255]);
+        assert!(from_hex_secret("").unwrap().is_empty());
+        assert!(from_hex_secret("0a0").is_err());
+        assert!(from_hex_secret("0g").is_err());
+        assert!(from_hex_secret("é0").is_err());
+    }
+
+    #[test]
+    fn test_from_hex_errors() {
+        assert_eq!(from_hex("0a0".to_string()), Err(Error::OddLength));
+        assert_eq!(from_hex("0x0".to_string()), Err(Error::OddLength));
+        assert_eq!(
+            from_hex("0g".to_string()),
+            Err(Error::InvalidChar { index: 1, ch: 'g' })
+        );
+        assert_eq!(
+            from_hex("0x00zz".to_string()),
+            Err(Error::InvalidChar { index: 4, ch: 'z' })
+        );
+        assert_eq!(
+            from_hex_secret("é0"),
+            Err(Error::InvalidChar { index: 0, ch: 'é' })
+        );
+        assert_eq!(from_hex("0X00".to_string()), Err(Error::InvalidPrefix));
+        assert_eq!(from_hex("x00".to_string()), Err(Error::InvalidPrefix));
+        assert!(from_hex("".to_string()).unwrap().is_empty());
+        assert_eq!(from_hex("0xABcd".to_string()).unwrap(), vec![0xab, 0xcd]);
+    }
+
+    #[test]
+    fn test_dump() {
+        assert_eq!(dump(&[]), "");
+        assert_eq!(
+            dump(b"hello\n"),
+            "00000000: 6865 6c6c 6f0a                           hello.\n"
+        );
+        assert_eq!(
+            dump(b"The quick brown fox jumps"),
+            "00000000: 5468 6520 7175 6963 6b20 6272 6f77 6e20  The quick brown \n\
+             00000010: 666f 7820 6a75 6d70 73                   fox jumps\n"
+        );
+
+        let mut out = String::new();
+        dump_to(&[0u8, 0xff], &mut out).unwrap();
+        assert_eq!(out, dump(&[0u8, 0xff]));
+    }
 }
@@ -0,0 +1,131 @@
+// Copyright 2021 Stichting Organism
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Unsigned LEB128 VarInt
+//!
+//! The encoding used by protobuf varints, WebAssembly and DWARF: seven bits
+//! per byte, least significant group first, with the high bit set on every
+//! byte but the last. Not wire compatible with `VarInt`, which is Bitcoin's
+//! CompactSize.
+
+use crate::ser::{Error, Readable, Reader, Writeable, Writer};
+
+/// Maximum encoded length of a u64
+const MAX_LEN: usize = 10;
+
+/// A variable-length unsigned integer in LEB128 encoding
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub struct Leb128(pub u64);
+
+#[allow(clippy::len_without_is_empty)]
+impl Leb128 {
+    /// Gets the length of this Leb128 when encoded, 1 to 10 bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        let bits = 64 - (self.0 | 1).leading_zeros() as usize;
+        bits.div_ceil(7)
+    }
+
+    #[inline]
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Readable for Leb128 {
+    /// Only the shortest encoding of a value is accepted.
+    fn read(reader: &mut dyn Reader) -> Result<Leb128, Error> {
+        let mut n = 0u64;
+        for i in 0..MAX_LEN {
+            let byte = reader.read_u8()?;
+            let group = (byte & 0x7F) as u64;
+
+            // The tenth byte only has room for the top bit of a u64
+            if i == MAX_LEN - 1 && byte > 1 {
+                return Err(Error::InvalidVarInt);
+            }
+            n |= group << (7 * i);
+
+            if byte & 0x80 == 0 {
+                // A trailing zero group means a shorter encoding existed
+                if i > 0 && byte == 0 {
+                    return Err(Error::InvalidVarInt);
+                }
+                return Ok(Leb128(n));
+            }
+        }
+        Err(Error::InvalidVarInt)
+    }
+}
+
+impl Writeable for Leb128 {
+    fn write<W: Writer>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut n = self.0;
+        loop {
+            let byte = (n & 0x7F) as u8;
+            n >>= 7;
+            if n == 0 {
+                return writer.write_u8(byte);
+            }
+            writer.write_u8(byte | 0x80)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser::{deserialize_default, ser_vec, ProtocolVersion};
+
+    fn round_trip(n: u64, bytes: &[u8]) {
+        let encoded = ser_vec(&Leb128(n), ProtocolVersion::local()).unwrap();
+        assert_eq!(encoded, bytes);
+        assert_eq!(Leb128(n).len(), bytes.len());
+
+        let decoded: Leb128 = deserialize_default(&mut &encoded[..]).unwrap();
+        assert_eq!(decoded, Leb128(n));
+    }
+
+    #[test]
+    fn test_leb128_round_trip() {
+        round_trip(0, &[0x00]);
+        round_trip(1, &[0x01]);
+        round_trip(127, &[0x7F]);
+        round_trip(128, &[0x80, 0x01]);
+        round_trip(300, &[0xAC, 0x02]);
+        round_trip(16383, &[0xFF, 0x7F]);
+        round_trip(16384, &[0x80, 0x80, 0x01]);
+        round_trip(0xFFFFFFFF, &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+        round_trip(
+            u64::MAX,
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01],
+        );
+    }
+
+    #[test]
+    fn test_leb128_invalid() {
+        let read = |bytes: &[u8]| deserialize_default::<Leb128>(&mut &bytes[..]);
+
+        // Non canonical, zero padded
+        assert_eq!(read(&[0x80, 0x00]), Err(Error::InvalidVarInt));
+        assert_eq!(read(&[0xFF, 0x80, 0x00]), Err(Error::InvalidVarInt));
+        // Overflows a u64
+        assert_eq!(
+            read(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02]),
+            Err(Error::InvalidVarInt)
+        );
+        // Truncated
+        assert!(read(&[0x80]).is_err());
+    }
+}
@@ -24,8 +24,11 @@ pub mod tai64;
 /// Repub Byteorder
 pub use byteorder;
 /// Variable Encoding Integer
-mod varint;
+pub mod varint;
 pub use varint::VarInt;
+/// LEB128 Variable Encoding Integer
+pub mod leb128;
+pub use leb128::Leb128;
 /// Export Curve
 pub use curve25519_dalek as dalek;
 /// Various Hash functions & types
@@ -36,18 +39,21 @@ pub use blake2b_simd as blake2;
 mod fast_merkle_root;
 /// That extra sauce
 pub mod tools;
-pub use fast_merkle_root::fast_merkle_root;
-
-
-
-uint::construct_uint! {
-    pub struct U256(4);
-}
-
+pub use fast_merkle_root::{
+    fast_merkle_path, fast_merkle_root, fast_merkle_root_btc, total_nodes, tree_height,
+    verify_merkle_path,
+};
+/// 256bit unsigned integer
+mod u256;
+pub use u256::{Error as U256Error, U256, U512};
 
 
 mod fisher_yates;
+pub use fisher_yates::FisherYatesState;
+#[cfg(all(feature = "rand_os", feature = "rand"))]
 pub use fisher_yates::fisher_yates;
+#[cfg(feature = "rand")]
+pub use fisher_yates::{fisher_yates_indexed, fisher_yates_with_rng};
 
 //
 // - Jeffrey Burdges <jeff@web3.foundation>
@@ -61,29 +67,89 @@ pub fn mohan_rand() -> impl rand::RngCore + rand::CryptoRng {
 
 #[cfg(all(feature = "rand_os", not(feature = "rand")))]
 pub fn mohan_rand() -> impl rand_core::RngCore + rand_core::CryptoRng {
-    ::rand_core::OsRng::new()
+    ::rand_core::OsRng
 }
 
-#[cfg(not(feature = "rand"))]
-pub fn mohan_rand() -> impl rand_core::RngCore + rand_core::CryptoRng {
-    const PRM: &'static str = "Attempted to use functionality that requires system randomness!!";
-
-    struct PanicRng;
-    impl ::rand_core::RngCore for PanicRng {
-        fn next_u32(&mut self) -> u32 {
-            panic!(&PRM)
-        }
-        fn next_u64(&mut self) -> u64 {
-            panic!(&PRM)
-        }
-        fn fill_bytes(&mut self, _dest: &mut [u8]) {
-            panic!(&PRM)
-        }
-        fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), ::rand_core::Error> {
-            panic!(&PRM)
-        }
+#[cfg(not(feature = "rand_os"))]
+const PRM: &str = "Attempted to use functionality that requires system randomness!!";
+
+#[cfg(not(feature = "rand_os"))]
+struct PanicRng;
+
+#[cfg(not(feature = "rand_os"))]
+impl ::rand_core::RngCore for PanicRng {
+    fn next_u32(&mut self) -> u32 {
+        panic!("{}", PRM)
+    }
+    fn next_u64(&mut self) -> u64 {
+        panic!("{}", PRM)
+    }
+    fn fill_bytes(&mut self, _dest: &mut [u8]) {
+        panic!("{}", PRM)
+    }
+    fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), ::rand_core::Error> {
+        panic!("{}", PRM)
     }
-    impl ::rand_core::CryptoRng for PanicRng {}
+}
+
+#[cfg(not(feature = "rand_os"))]
+impl ::rand_core::CryptoRng for PanicRng {}
 
+#[cfg(not(feature = "rand_os"))]
+pub fn mohan_rand() -> impl rand_core::RngCore + rand_core::CryptoRng {
     PanicRng
 }
+
+/// Returned by `try_mohan_rand` when the crate was built without a source
+/// of system randomness
+#[derive(Copy, Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("no system randomness available")]
+pub struct NoRandomness;
+
+/// Like `mohan_rand`, but reports missing randomness as an error rather
+/// than handing out a generator that panics on first use.
+#[cfg(all(feature = "rand_os", feature = "rand"))]
+pub fn try_mohan_rand() -> Result<impl rand::RngCore + rand::CryptoRng, NoRandomness> {
+    Ok(mohan_rand())
+}
+
+/// Like `mohan_rand`, but reports missing randomness as an error rather
+/// than handing out a generator that panics on first use.
+#[cfg(all(feature = "rand_os", not(feature = "rand")))]
+pub fn try_mohan_rand() -> Result<impl rand_core::RngCore + rand_core::CryptoRng, NoRandomness> {
+    Ok(::rand_core::OsRng)
+}
+
+/// Like `mohan_rand`, but reports missing randomness as an error rather
+/// than handing out a generator that panics on first use.
+#[cfg(not(feature = "rand_os"))]
+pub fn try_mohan_rand() -> Result<impl rand_core::RngCore + rand_core::CryptoRng, NoRandomness> {
+    Err::<PanicRng, _>(NoRandomness)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(all(feature = "rand_os", feature = "rand"))]
+    #[test]
+    fn test_try_mohan_rand() {
+        use rand::RngCore;
+        let mut rng = try_mohan_rand().unwrap();
+        assert_ne!(rng.next_u64(), rng.next_u64());
+    }
+
+    #[cfg(all(feature = "rand_os", not(feature = "rand")))]
+    #[test]
+    fn test_try_mohan_rand() {
+        use rand_core::RngCore;
+        let mut rng = try_mohan_rand().unwrap();
+        assert_ne!(rng.next_u64(), rng.next_u64());
+    }
+
+    #[cfg(not(feature = "rand_os"))]
+    #[test]
+    fn test_try_mohan_rand() {
+        assert_eq!(try_mohan_rand().err(), Some(NoRandomness));
+    }
+}
@@ -20,6 +20,7 @@
 //! `serialize` or `deserialize` functions on them as appropriate.
 
 // use crate::hash::Hashed;
+use crate::varint::VarInt;
 use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
 use thiserror::Error;
 use serde::{Deserialize, Serialize};
@@ -54,6 +55,14 @@ pub enum Error {
     /// Data wasn't in a consumable format
     #[error("corrupted data")]
     CorruptedData,
+    /// Fixed length data had the wrong length
+    #[error("invalid length, expected {expected} got {received}")]
+    InvalidLength {
+        /// The length we wanted
+        expected: usize,
+        /// The length we got
+        received: usize,
+    },
     /// Incorrect number of elements (when deserializing a vec via read_multi say).
     #[error("count error")]
     CountError,
@@ -75,6 +84,9 @@ pub enum Error {
     /// Block header version (hard-fork schedule).
     #[error("invalid variable integer encoding")]
     InvalidVarInt,
+    /// When asked to write more data than the format allows
+    #[error("too large write")]
+    TooLargeWriteErr,
 }
 
 impl From<io::Error> for Error {
@@ -84,11 +96,19 @@ impl From<io::Error> for Error {
 }
 
 /// Signal to a serializable object how much of its data should be serialized
-#[derive(Copy, Clone, PartialEq, Eq)]
+///
+/// The mode only selects what is written, never how: every `Writer` here
+/// encodes integers little-endian whatever the mode, and every `Reader`
+/// expects the same. Readers have no mode, so whatever a `Writeable` emits
+/// in `Full` mode is what its `Readable` must parse back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum SerializationMode {
-    /// Serialize everything sufficiently to fully reconstruct the object
+    /// Serialize everything sufficiently to fully reconstruct the object.
+    /// Used by `BinWriter`, and so by `serialize` and `ser_vec`.
     Full,
-    /// Serialize the data that defines the object
+    /// Serialize the data that defines the object. Used by `HashWriter`,
+    /// the output is hashed and never read back, so data that does not
+    /// contribute to the identity of the object may be left out.
     Hash,
 }
 
@@ -225,6 +245,9 @@ where
     }
 }
 
+/// Largest element count `read_multi` and `Readable for Vec<T>` accept.
+const MAX_READ_COUNT: u64 = 1_000_000;
+
 /// Reads multiple serialized items into a Vec.
 pub fn read_multi<T>(reader: &mut dyn Reader, count: u64) -> Result<Vec<T>, Error>
 where
@@ -233,7 +256,7 @@ where
     // Very rudimentary check to ensure we do not overflow anything
     // attempting to read huge amounts of data.
     // Probably better than checking if count * size overflows a u64 though.
-    if count > 1_000_000 {
+    if count > MAX_READ_COUNT {
         return Err(Error::TooLargeReadErr);
     }
 
@@ -495,6 +518,91 @@ impl<'a> Reader for StreamingReader<'a> {
     }
 }
 
+/// A reader that caps the total number of bytes that can be read through it.
+/// Once the budget is exhausted every further read fails with
+/// `Error::TooLargeReadErr`, whatever length fields in the data claim.
+/// Useful as a last line of defense when parsing untrusted input.
+pub struct BudgetReader<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Reader> BudgetReader<R> {
+    /// Wrap `inner`, allowing at most `budget` bytes to be read.
+    pub fn new(inner: R, budget: u64) -> BudgetReader<R> {
+        BudgetReader {
+            inner,
+            remaining: budget,
+        }
+    }
+
+    /// Returns how many bytes can still be read.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn consume(&mut self, len: u64) -> Result<(), Error> {
+        if len > self.remaining {
+            return Err(Error::TooLargeReadErr);
+        }
+        self.remaining -= len;
+        Ok(())
+    }
+}
+
+impl<R: Reader> Reader for BudgetReader<R> {
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        self.consume(1)?;
+        self.inner.read_u8()
+    }
+    fn read_u16(&mut self) -> Result<u16, Error> {
+        self.consume(2)?;
+        self.inner.read_u16()
+    }
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        self.consume(4)?;
+        self.inner.read_u32()
+    }
+    fn read_i32(&mut self) -> Result<i32, Error> {
+        self.consume(4)?;
+        self.inner.read_i32()
+    }
+    fn read_u64(&mut self) -> Result<u64, Error> {
+        self.consume(8)?;
+        self.inner.read_u64()
+    }
+    fn read_i64(&mut self) -> Result<i64, Error> {
+        self.consume(8)?;
+        self.inner.read_i64()
+    }
+
+    /// Read a variable size vector, checking the prefixed length against the
+    /// budget before reading the bytes.
+    fn read_bytes_len_prefix(&mut self) -> Result<Vec<u8>, Error> {
+        let len = self.read_u64()?;
+        self.read_fixed_bytes(len as usize)
+    }
+
+    fn read_fixed_bytes(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        self.consume(len as u64)?;
+        self.inner.read_fixed_bytes(len)
+    }
+
+    fn expect_u8(&mut self, val: u8) -> Result<u8, Error> {
+        self.consume(1)?;
+        self.inner.expect_u8(val)
+    }
+
+    fn protocol_version(&self) -> ProtocolVersion {
+        self.inner.protocol_version()
+    }
+}
+
 /// Utility wrapper for an underlying byte Writer. Defines higher level methods
 /// to write numbers, byte vectors, hashes, etc.
 pub struct BinWriter<'a> {
@@ -553,31 +661,35 @@ impl_int!(i32, write_i32, read_i32);
 impl_int!(u64, write_u64, read_u64);
 impl_int!(i64, write_i64, read_i64);
 
+/// Reads the `VarInt` element count written by `Writeable for Vec<T>`
+/// followed by exactly that many elements. Counts above the `read_multi`
+/// limit are refused with `TooLargeReadErr` before anything is read.
 impl<T> Readable for Vec<T>
 where
     T: Readable,
 {
     fn read(reader: &mut dyn Reader) -> Result<Vec<T>, Error> {
+        let VarInt(count) = VarInt::read(reader)?;
+        if count > MAX_READ_COUNT {
+            return Err(Error::TooLargeReadErr);
+        }
         let mut buf = Vec::new();
-        loop {
-            let elem = T::read(reader);
-            match elem {
-                Ok(e) => buf.push(e),
-                Err(Error::IOErr(ref _d, ref kind)) if *kind == io::ErrorKind::UnexpectedEof => {
-                    break;
-                }
-                Err(e) => return Err(e),
-            }
+        for _ in 0..count {
+            buf.push(T::read(reader)?);
         }
         Ok(buf)
     }
 }
 
+/// Vectors, including `Vec<u8>`, are written as a `VarInt` element count
+/// followed by the elements. The count keeps adjacent vectors apart when
+/// hashing, so `[1] ++ [2, 3]` and `[1, 2] ++ [3]` hash differently.
 impl<T> Writeable for Vec<T>
 where
     T: Writeable,
 {
     fn write<W: Writer>(&self, writer: &mut W) -> Result<(), Error> {
+        VarInt(self.len() as u64).write(writer)?;
         for elmt in self {
             elmt.write(writer)?;
         }
@@ -651,9 +763,20 @@ impl<A: Readable, B: Readable, C: Readable, D: Readable> Readable for (A, B, C,
     }
 }
 
-impl Writeable for [u8; 4] {
+/// Fixed size byte arrays, such as keys and nonces, are written as their
+/// raw `N` bytes with no length prefix; the reader knows `N` from the type.
+impl<const N: usize> Writeable for [u8; N] {
     fn write<W: Writer>(&self, writer: &mut W) -> Result<(), Error> {
-        writer.write_bytes(self)
+        writer.write_fixed_bytes(self)
+    }
+}
+
+impl<const N: usize> Readable for [u8; N] {
+    fn read(reader: &mut dyn Reader) -> Result<[u8; N], Error> {
+        let v = reader.read_fixed_bytes(N)?;
+        let mut a = [0u8; N];
+        a.copy_from_slice(&v);
+        Ok(a)
     }
 }
 
@@ -713,51 +836,9 @@ impl AsFixedBytes for Vec<u8> {
     }
 }
 
-impl AsFixedBytes for [u8; 1] {
-    fn len(&self) -> usize {
-        1
-    }
-}
-
-impl AsFixedBytes for [u8; 2] {
+impl<const N: usize> AsFixedBytes for [u8; N] {
     fn len(&self) -> usize {
-        2
-    }
-}
-
-impl AsFixedBytes for [u8; 4] {
-    fn len(&self) -> usize {
-        4
-    }
-}
-
-impl AsFixedBytes for [u8; 6] {
-    fn len(&self) -> usize {
-        6
-    }
-}
-
-impl AsFixedBytes for [u8; 8] {
-    fn len(&self) -> usize {
-        8
-    }
-}
-
-impl AsFixedBytes for [u8; 16] {
-    fn len(&self) -> usize {
-        16
-    }
-}
-
-impl AsFixedBytes for [u8; 20] {
-    fn len(&self) -> usize {
-        20
-    }
-}
-
-impl AsFixedBytes for [u8; 32] {
-    fn len(&self) -> usize {
-        32
+        N
     }
 }
 
@@ -1209,3 +1290,152 @@ where
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{HashWriter, Hashed};
+
+    /// Carries a cached value that is stored but is not part of its identity
+    #[derive(Debug, PartialEq)]
+    struct Cached {
+        id: u64,
+        cache: u32,
+    }
+
+    impl Writeable for Cached {
+        fn write<W: Writer>(&self, writer: &mut W) -> Result<(), Error> {
+            writer.write_u64(self.id)?;
+            if writer.serialization_mode() == SerializationMode::Full {
+                writer.write_u32(self.cache)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl Readable for Cached {
+        fn read(reader: &mut dyn Reader) -> Result<Cached, Error> {
+            Ok(Cached {
+                id: reader.read_u64()?,
+                cache: reader.read_u32()?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_serialization_modes() {
+        let c = Cached {
+            id: 0x0102,
+            cache: 7,
+        };
+
+        // Full mode writes everything, little-endian, and round trips
+        let full = ser_vec(&c, ProtocolVersion::local()).unwrap();
+        assert_eq!(full, vec![2, 1, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0]);
+        let back: Cached = deserialize_default(&mut &full[..]).unwrap();
+        assert_eq!(back, c);
+
+        // Hash mode only sees the identity
+        let mut hasher = HashWriter::default();
+        assert_eq!(hasher.serialization_mode(), SerializationMode::Hash);
+        c.write(&mut hasher).unwrap();
+        assert_eq!(hasher.into_hash(), 0x0102u64.hash());
+    }
+
+    #[test]
+    fn test_budget_reader() {
+        let data = ser_vec(&(1u64, 2u32), ProtocolVersion::local()).unwrap();
+
+        let mut source = &data[..];
+        let reader = BinReader::new(&mut source, ProtocolVersion::local());
+        let mut reader = BudgetReader::new(reader, 12);
+        assert_eq!(reader.read_u64().unwrap(), 1);
+        assert_eq!(reader.read_u32().unwrap(), 2);
+        assert_eq!(reader.remaining(), 0);
+        assert_eq!(reader.protocol_version(), ProtocolVersion::local());
+
+        let mut source = &data[..];
+        let reader = BinReader::new(&mut source, ProtocolVersion::local());
+        let mut reader = BudgetReader::new(reader, 11);
+        assert_eq!(reader.read_u64().unwrap(), 1);
+        assert_eq!(reader.read_u32(), Err(Error::TooLargeReadErr));
+    }
+
+    #[test]
+    fn test_budget_reader_len_prefix() {
+        // Claims 90k bytes follow, well under BinReader's own cap
+        let data = ser_vec(&90_000u64, ProtocolVersion::local()).unwrap();
+
+        let mut source = &data[..];
+        let reader = BinReader::new(&mut source, ProtocolVersion::local());
+        let mut reader = BudgetReader::new(reader, 1_000);
+        assert_eq!(reader.read_bytes_len_prefix(), Err(Error::TooLargeReadErr));
+    }
+
+    #[test]
+    fn test_vec_len_prefix() {
+        let v = vec![1u8, 2, 3];
+        let data = ser_vec(&v, ProtocolVersion::local()).unwrap();
+        assert_eq!(data, vec![3, 1, 2, 3]);
+        let back: Vec<u8> = deserialize_default(&mut &data[..]).unwrap();
+        assert_eq!(back, v);
+
+        // Trailing data is left for the next read
+        let data = ser_vec(&(vec![7u32], 9u8), ProtocolVersion::local()).unwrap();
+        let back: (Vec<u32>, u8) = deserialize_default(&mut &data[..]).unwrap();
+        assert_eq!(back, (vec![7u32], 9u8));
+
+        assert_ne!(
+            (vec![1u8], vec![2u8, 3]).hash(),
+            (vec![1u8, 2], vec![3u8]).hash()
+        );
+    }
+
+    #[test]
+    fn test_vec_count_limit() {
+        // A count just over the limit is refused without reading elements
+        let data = ser_vec(&VarInt(1_000_001), ProtocolVersion::local()).unwrap();
+        let res: Result<Vec<u8>, Error> = deserialize_default(&mut &data[..]);
+        assert_eq!(res, Err(Error::TooLargeReadErr));
+
+        let data = ser_vec(&VarInt(u64::MAX), ProtocolVersion::local()).unwrap();
+        let res: Result<Vec<u64>, Error> = deserialize_default(&mut &data[..]);
+        assert_eq!(res, Err(Error::TooLargeReadErr));
+
+        // At the limit the count is trusted and the elements are read
+        let mut data = ser_vec(&VarInt(1_000_000), ProtocolVersion::local()).unwrap();
+        data.extend(vec![0u8; 1_000_000]);
+        let back: Vec<u8> = deserialize_default(&mut &data[..]).unwrap();
+        assert_eq!(back.len(), 1_000_000);
+    }
+
+    #[test]
+    fn test_byte_array_round_trip() {
+        let nonce: [u8; 12] = [7; 12];
+        let data = ser_vec(&nonce, ProtocolVersion::local()).unwrap();
+        assert_eq!(data, nonce.to_vec());
+        let back: [u8; 12] = deserialize_default(&mut &data[..]).unwrap();
+        assert_eq!(back, nonce);
+
+        let mut wide = [0u8; 64];
+        for (i, b) in wide.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let data = ser_vec(&wide, ProtocolVersion::local()).unwrap();
+        assert_eq!(data.len(), 64);
+        let back: [u8; 64] = deserialize_default(&mut &data[..]).unwrap();
+        assert_eq!(back[..], wide[..]);
+
+        // Short input is an error rather than a partly filled array
+        let short: Result<[u8; 64], Error> = deserialize_default(&mut &data[..63]);
+        assert!(short.is_err());
+    }
+
+    #[test]
+    fn test_byte_array_wire_format() {
+        // Raw bytes with no length prefix, pinned so the format can't drift
+        let data = ser_vec(&[1u8, 2, 3, 4], ProtocolVersion::local()).unwrap();
+        assert_eq!(data, vec![1, 2, 3, 4]);
+        assert_eq!(ser_vec(&[0u8; 0], ProtocolVersion::local()).unwrap(), vec![]);
+    }
+}
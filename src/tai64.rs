@@ -18,7 +18,7 @@
 use thiserror::Error;
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{convert::TryFrom, ops, time::Duration};
+use std::{convert::TryFrom, io, ops, time::Duration};
 
 /// Unix epoch in TAI64: 1970-01-01 00:00:10 TAI.
 pub const UNIX_EPOCH_TAI64: TAI64 = TAI64(10 + (1 << 62));
@@ -164,6 +164,72 @@ impl TAI64N {
         }
     }
 
+    /// How much time passes since the `other` timestamp, or zero if `other`
+    /// is later than `self`, like `Instant::saturating_duration_since`.
+    pub fn saturating_duration_since(&self, other: &TAI64N) -> Duration {
+        self.duration_since(other).unwrap_or(Duration::from_secs(0))
+    }
+
+    /// How much time has passed since this timestamp according to the
+    /// system clock, zero if it lies in the future.
+    pub fn elapsed(&self) -> Duration {
+        TAI64N::now().saturating_duration_since(self)
+    }
+
+    /// Add a `Duration`, returning `None` if the seconds part would overflow.
+    pub fn checked_add(&self, d: Duration) -> Option<TAI64N> {
+        let n = self.1 + d.subsec_nanos();
+
+        let (carry, n) = if n >= NANOS_PER_SECOND {
+            (1, n - NANOS_PER_SECOND)
+        } else {
+            (0, n)
+        };
+
+        let s = (self.0).0.checked_add(d.as_secs())?.checked_add(carry)?;
+        Some(TAI64N(TAI64(s), n))
+    }
+
+    /// Subtract a `Duration`, returning `None` if the result would be before
+    /// the TAI64 zero label.
+    pub fn checked_sub(&self, d: Duration) -> Option<TAI64N> {
+        let (carry, n) = if self.1 >= d.subsec_nanos() {
+            (0, self.1 - d.subsec_nanos())
+        } else {
+            (1, NANOS_PER_SECOND + self.1 - d.subsec_nanos())
+        };
+
+        let s = (self.0).0.checked_sub(carry)?.checked_sub(d.as_secs())?;
+        Some(TAI64N(TAI64(s), n))
+    }
+
+    /// Convert nanoseconds since the Unix epoch, negative before it, to
+    /// `TAI64N`, returning `None` if the whole seconds do not fit the range
+    /// `TAI64::from_unix` accepts.
+    pub fn checked_from_unix_nanos(nanos: i128) -> Option<TAI64N> {
+        let per_sec = NANOS_PER_SECOND as i128;
+        let secs = i64::try_from(nanos.div_euclid(per_sec)).ok()?;
+        secs.checked_add(UNIX_EPOCH_TAI64.0 as i64)?;
+        let n = nanos.rem_euclid(per_sec) as u32;
+        Some(TAI64N(TAI64::from_unix(secs), n))
+    }
+
+    /// Convert nanoseconds since the Unix epoch, negative before it, to
+    /// `TAI64N`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `checked_from_unix_nanos` would return `None`.
+    pub fn from_unix_nanos(nanos: i128) -> TAI64N {
+        TAI64N::checked_from_unix_nanos(nanos).expect("unix nanos out of range for TAI64N")
+    }
+
+    /// Convert `TAI64N` to nanoseconds since the Unix epoch, negative
+    /// before it.
+    pub fn to_unix_nanos(&self) -> i128 {
+        self.0.to_unix() as i128 * NANOS_PER_SECOND as i128 + self.1 as i128
+    }
+
     /// Convert `SystemTime` to `TAI64N`.
     pub fn from_system_time(t: &SystemTime) -> Self {
         match t.duration_since(UNIX_EPOCH) {
@@ -223,32 +289,51 @@ impl From<SystemTime> for TAI64N {
     }
 }
 
+/// # Panics
+///
+/// Panics if the result does not fit in a `TAI64N`, rather than wrapping
+/// around to a distant past. Use `TAI64N::checked_add` to handle that case.
 impl ops::Add<Duration> for TAI64N {
     type Output = TAI64N;
 
     fn add(self, d: Duration) -> TAI64N {
-        let n = self.1 + d.subsec_nanos();
-
-        let (carry, n) = if n >= NANOS_PER_SECOND {
-            (1, n - NANOS_PER_SECOND)
-        } else {
-            (0, n)
-        };
-
-        TAI64N(self.0 + d.as_secs() + carry, n)
+        self.checked_add(d)
+            .expect("overflow when adding duration to TAI64N")
     }
 }
 
+/// # Panics
+///
+/// Panics if the result would be before the TAI64 zero label. Use
+/// `TAI64N::checked_sub` to handle that case.
 impl ops::Sub<Duration> for TAI64N {
     type Output = TAI64N;
 
     fn sub(self, d: Duration) -> TAI64N {
-        let (carry, n) = if self.1 >= d.subsec_nanos() {
-            (0, self.1 - d.subsec_nanos())
-        } else {
-            (1, NANOS_PER_SECOND + self.1 - d.subsec_nanos())
-        };
-        TAI64N(self.0 - carry - d.as_secs(), n)
+        self.checked_sub(d)
+            .expect("overflow when subtracting duration from TAI64N")
+    }
+}
+
+/// Parse an external TAI64 or TAI64N label written in hex, such as
+/// `@4000000037c219bf2ef02e94`. The leading `@` is optional. A 16 digit
+/// TAI64 label is promoted to a `TAI64N` with zero nanoseconds, a 24 digit
+/// label is read as a full `TAI64N`.
+pub fn parse_label(s: &str) -> Result<TAI64N, Error> {
+    let digits = s.strip_prefix('@').unwrap_or(s);
+    if digits.len() != 2 * TAI64_LEN && digits.len() != 2 * TAI64N_LEN {
+        return Err(Error::LengthInvalid);
+    }
+    // from_hex would also take a 0x prefix
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::LabelInvalid);
+    }
+
+    let bytes = crate::hex::from_hex(digits.to_string()).map_err(|_| Error::LabelInvalid)?;
+    if bytes.len() == TAI64_LEN {
+        Ok(TAI64::from_slice(&bytes)?.into())
+    } else {
+        TAI64N::from_slice(&bytes)
     }
 }
 
@@ -262,6 +347,10 @@ pub enum Error {
     /// Nanosecond part must be <= 999999999.
     #[error("invalid number of nanoseconds")]
     NanosInvalid,
+
+    /// Label is not made of hex digits
+    #[error("label invalid")]
+    LabelInvalid,
 }
 
 //
@@ -281,6 +370,121 @@ impl crate::ser::Writeable for TAI64 {
     }
 }
 
+//
+// Log Framing
+//
+
+/// Largest payload a `LogWriter` writes or a `LogReader` accepts unless
+/// told otherwise.
+pub const MAX_LOG_PAYLOAD: usize = 100_000;
+
+/// Appends records framed as `TAI64N || VarInt length || payload` to an
+/// underlying stream. The timestamp uses the external 12 byte encoding.
+pub struct LogWriter<W: io::Write> {
+    inner: W,
+    max_payload: usize,
+}
+
+impl<W: io::Write> LogWriter<W> {
+    /// Write records to `inner`, refusing payloads over `MAX_LOG_PAYLOAD`
+    pub fn new(inner: W) -> LogWriter<W> {
+        LogWriter::with_max_payload(inner, MAX_LOG_PAYLOAD)
+    }
+
+    /// Write records to `inner`, refusing payloads over `max_payload`
+    pub fn with_max_payload(inner: W, max_payload: usize) -> LogWriter<W> {
+        LogWriter { inner, max_payload }
+    }
+
+    /// Append a single record. A payload over the limit is refused with
+    /// `TooLargeWriteErr`, since a `LogReader` with the same limit would
+    /// reject it, and nothing is written.
+    pub fn append(&mut self, ts: TAI64N, payload: &[u8]) -> Result<(), crate::ser::Error> {
+        use crate::ser::Writeable;
+
+        if payload.len() > self.max_payload {
+            return Err(crate::ser::Error::TooLargeWriteErr);
+        }
+
+        self.inner.write_all(&ts.to_bytes())?;
+        let mut writer = crate::ser::BinWriter::default(&mut self.inner);
+        crate::varint::VarInt(payload.len() as u64).write(&mut writer)?;
+        self.inner.write_all(payload)?;
+        Ok(())
+    }
+
+    /// Unwrap the underlying stream
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads back the records written by a `LogWriter`, in order. Iterating
+/// yields each record and stops for good after the first error.
+pub struct LogReader<R: io::Read> {
+    inner: R,
+    max_payload: usize,
+    failed: bool,
+}
+
+impl<R: io::Read> LogReader<R> {
+    /// Read records from `inner`, refusing payloads over `MAX_LOG_PAYLOAD`
+    pub fn new(inner: R) -> LogReader<R> {
+        LogReader::with_max_payload(inner, MAX_LOG_PAYLOAD)
+    }
+
+    /// Read records from `inner`, refusing payloads over `max_payload`
+    pub fn with_max_payload(inner: R, max_payload: usize) -> LogReader<R> {
+        LogReader {
+            inner,
+            max_payload,
+            failed: false,
+        }
+    }
+
+    /// Read the next record, `None` when the stream ends cleanly between
+    /// records. A stream cut off inside a record is an error.
+    pub fn read_record(&mut self) -> Result<Option<(TAI64N, Vec<u8>)>, crate::ser::Error> {
+        let mut ts = [0u8; TAI64N_LEN];
+        loop {
+            match self.inner.read(&mut ts[..1]) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.inner.read_exact(&mut ts[1..])?;
+        let ts = TAI64N::try_from(ts).map_err(|_| crate::ser::Error::CorruptedData)?;
+
+        let len = crate::varint::VarInt::read_from(&mut self.inner)?.0;
+        if len > self.max_payload as u64 {
+            return Err(crate::ser::Error::TooLargeReadErr);
+        }
+        let mut payload = vec![0u8; len as usize];
+        self.inner.read_exact(&mut payload)?;
+        Ok(Some((ts, payload)))
+    }
+
+    /// Unwrap the underlying stream
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: io::Read> Iterator for LogReader<R> {
+    type Item = Result<(TAI64N, Vec<u8>), crate::ser::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let res = self.read_record();
+        self.failed = res.is_err();
+        res.transpose()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -324,6 +528,210 @@ mod tests {
         assert_eq!(t, t1);
     }
 
+    #[test]
+    fn checked_add_overflow() {
+        let near_max = TAI64N(TAI64(u64::MAX - 1), 999_999_999);
+
+        assert_eq!(
+            near_max.checked_add(Duration::new(1, 0)),
+            Some(TAI64N(TAI64(u64::MAX), 999_999_999))
+        );
+        assert_eq!(near_max.checked_add(Duration::new(1, 1)), None);
+        assert_eq!(near_max.checked_add(Duration::new(u64::MAX, 0)), None);
+    }
+
+    #[test]
+    fn checked_sub_underflow() {
+        let near_zero = TAI64N(TAI64(1), 0);
+
+        assert_eq!(
+            near_zero.checked_sub(Duration::new(0, 1)),
+            Some(TAI64N(TAI64(0), 999_999_999))
+        );
+        assert_eq!(near_zero.checked_sub(Duration::new(1, 1)), None);
+        assert_eq!(near_zero.checked_sub(Duration::new(u64::MAX, 0)), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_overflow_panics() {
+        let _ = TAI64N(TAI64(u64::MAX), 0) + Duration::new(1, 0);
+    }
+
+    #[test]
+    fn unix_nanos_out_of_range() {
+        let per_sec = NANOS_PER_SECOND as i128;
+        let max_secs = (i64::MAX - UNIX_EPOCH_TAI64.0 as i64) as i128;
+
+        let last = max_secs * per_sec + per_sec - 1;
+        let t = TAI64N::checked_from_unix_nanos(last).unwrap();
+        assert_eq!(t.to_unix_nanos(), last);
+        assert_eq!(TAI64N::checked_from_unix_nanos(last + 1), None);
+
+        let first = i64::MIN as i128 * per_sec;
+        let t = TAI64N::checked_from_unix_nanos(first).unwrap();
+        assert_eq!(t.to_unix_nanos(), first);
+        assert_eq!(TAI64N::checked_from_unix_nanos(first - 1), None);
+
+        assert_eq!(TAI64N::checked_from_unix_nanos(i128::MAX / 2), None);
+        assert_eq!(TAI64N::checked_from_unix_nanos(i128::MIN), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "unix nanos out of range")]
+    fn from_unix_nanos_out_of_range_panics() {
+        let _ = TAI64N::from_unix_nanos(i128::MAX / 2);
+    }
+
+    #[test]
+    fn unix_nanos() {
+        assert_eq!(TAI64N::from_unix_nanos(0), UNIX_EPOCH_TAI64N);
+        assert_eq!(
+            TAI64N::from_unix_nanos(-1),
+            TAI64N(TAI64::from_unix(-1), 999_999_999)
+        );
+        assert_eq!(
+            TAI64N::from_unix_nanos(1_500_000_001),
+            TAI64N(TAI64::from_unix(1), 500_000_001)
+        );
+
+        // The same instants as the before_epoch test, and their mirrors
+        let per_sec = NANOS_PER_SECOND as u128;
+        for &abs in [0u128, 1, 73_234_068_416_841, 488_294_802_189_999_999_999].iter() {
+            let d = Duration::new((abs / per_sec) as u64, (abs % per_sec) as u32);
+            let cases = [
+                (abs as i128, UNIX_EPOCH + d),
+                (-(abs as i128), UNIX_EPOCH - d),
+            ];
+            for &(nanos, st) in cases.iter() {
+                let t = TAI64N::from_unix_nanos(nanos);
+                assert_eq!(t.to_unix_nanos(), nanos);
+                assert_eq!(TAI64N::from_system_time(&st), t);
+            }
+        }
+    }
+
+    #[test]
+    fn saturating_duration_since() {
+        let earlier = TAI64N(TAI64(1 << 62), 900_000_000);
+        let later = TAI64N(TAI64((1 << 62) + 2), 100_000_000);
+
+        assert_eq!(
+            later.saturating_duration_since(&earlier),
+            Duration::new(1, 200_000_000)
+        );
+        assert_eq!(
+            earlier.saturating_duration_since(&later),
+            Duration::from_secs(0)
+        );
+        assert_eq!(
+            later.saturating_duration_since(&later),
+            Duration::from_secs(0)
+        );
+
+        let past = TAI64N::now() - Duration::from_secs(60);
+        assert!(past.elapsed() >= Duration::from_secs(60));
+        let future = TAI64N::now() + Duration::from_secs(3600);
+        assert_eq!(future.elapsed(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn parse_labels() {
+        let secs = TAI64(0x4000000037c219bf);
+        assert_eq!(
+            parse_label("@4000000037c219bf2ef02e94"),
+            Ok(TAI64N(secs, 0x2ef02e94))
+        );
+        assert_eq!(
+            parse_label("4000000037c219bf2ef02e94"),
+            Ok(TAI64N(secs, 0x2ef02e94))
+        );
+        assert_eq!(parse_label("@4000000037c219bf"), Ok(TAI64N(secs, 0)));
+        assert_eq!(parse_label("4000000037c219bf"), Ok(TAI64N(secs, 0)));
+
+        assert_eq!(parse_label("4000000037c219b"), Err(Error::LengthInvalid));
+        assert_eq!(
+            parse_label("@4000000037c219bf2ef02e9"),
+            Err(Error::LengthInvalid)
+        );
+        assert_eq!(parse_label(""), Err(Error::LengthInvalid));
+        assert_eq!(parse_label("400000zz37c219bf"), Err(Error::LabelInvalid));
+        assert_eq!(parse_label("0x00000037c219bf"), Err(Error::LabelInvalid));
+        assert_eq!(
+            parse_label("4000000037c219bfffffffff"),
+            Err(Error::NanosInvalid)
+        );
+    }
+
+    #[test]
+    fn log_round_trip() {
+        let records = vec![
+            (TAI64N(TAI64(1 << 62), 5), b"first".to_vec()),
+            (TAI64N(TAI64((1 << 62) + 1), 0), vec![]),
+            (TAI64N(TAI64((1 << 62) + 2), 999_999_999), vec![7u8; 300]),
+        ];
+
+        let mut writer = LogWriter::new(vec![]);
+        for (ts, payload) in records.iter() {
+            writer.append(*ts, payload).unwrap();
+        }
+        let log = writer.into_inner();
+        assert_eq!(log.len(), 3 * TAI64N_LEN + (1 + 5) + 1 + (3 + 300));
+
+        let read: Result<Vec<_>, _> = LogReader::new(&log[..]).collect();
+        assert_eq!(read.unwrap(), records);
+
+        // Cut off inside the last record
+        let mut reader = LogReader::new(&log[..log.len() - 1]);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+
+        // Payload over the cap
+        let mut reader = LogReader::with_max_payload(&log[..], 100);
+        reader.read_record().unwrap();
+        reader.read_record().unwrap();
+        assert_eq!(
+            reader.read_record(),
+            Err(crate::ser::Error::TooLargeReadErr)
+        );
+    }
+
+    #[test]
+    fn log_writer_payload_limit() {
+        let ts = TAI64N(TAI64(1 << 62), 0);
+
+        // At the limit the writer's output reads back
+        let mut writer = LogWriter::new(vec![]);
+        writer.append(ts, &vec![1u8; MAX_LOG_PAYLOAD]).unwrap();
+        let log = writer.into_inner();
+        let mut reader = LogReader::new(&log[..]);
+        assert_eq!(
+            reader.read_record().unwrap(),
+            Some((ts, vec![1u8; MAX_LOG_PAYLOAD]))
+        );
+        assert_eq!(reader.read_record().unwrap(), None);
+
+        // Just over it the writer refuses the record and writes nothing
+        let mut writer = LogWriter::new(vec![]);
+        assert_eq!(
+            writer.append(ts, &vec![1u8; MAX_LOG_PAYLOAD + 1]),
+            Err(crate::ser::Error::TooLargeWriteErr)
+        );
+        assert!(writer.into_inner().is_empty());
+
+        let mut writer = LogWriter::with_max_payload(vec![], 3);
+        writer.append(ts, b"abc").unwrap();
+        assert_eq!(
+            writer.append(ts, b"abcd"),
+            Err(crate::ser::Error::TooLargeWriteErr)
+        );
+        let log = writer.into_inner();
+        let read: Result<Vec<_>, _> = LogReader::with_max_payload(&log[..], 3).collect();
+        assert_eq!(read.unwrap(), vec![(ts, b"abc".to_vec())]);
+    }
+
     impl Arbitrary for TAI64N {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             let s = u64::arbitrary(g);
@@ -352,6 +760,11 @@ mod tests {
             true
         }
 
+        fn unix_nanos_round_trip(secs: i32, offset: i64) -> bool {
+            let nanos = secs as i128 * NANOS_PER_SECOND as i128 + offset as i128;
+            TAI64N::from_unix_nanos(nanos).to_unix_nanos() == nanos
+        }
+
         fn to_from_system_time(before_epoch: bool, d: Duration) -> bool {
             let st = if before_epoch {
                 UNIX_EPOCH + d
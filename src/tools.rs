@@ -20,6 +20,14 @@
 use crate::dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use crate::ser;
 use std::fmt::Debug;
+use subtle::ConstantTimeEq;
+
+/// Compare two byte slices in time that depends only on their lengths,
+/// for tags, hashes and other values an attacker must not learn byte by
+/// byte. Slices of different lengths are never equal.
+pub fn ct_eq_bytes(a: &[u8], b: &[u8]) -> bool {
+    bool::from(a.ct_eq(b))
+}
 
 /// Compressed Ristretto point length
 pub const RISTRETTO_POINT_LENGTH: usize = 32;
@@ -86,6 +94,12 @@ impl RistrettoBoth {
         }
     }
 
+    /// A uniformly random point, not a known multiple of the basepoint.
+    #[cfg(feature = "rand")]
+    pub fn random<R: rand::RngCore + rand::CryptoRng>(rng: &mut R) -> RistrettoBoth {
+        RistrettoBoth::from_point(RistrettoPoint::random(rng))
+    }
+
     /// Convert this point to a byte array.
     #[inline]
     pub fn to_bytes(&self) -> [u8; RISTRETTO_POINT_LENGTH] {
@@ -176,6 +190,10 @@ impl PartialOrd<RistrettoBoth> for RistrettoBoth {
     // }
 }
 
+/// Points are ordered by their compressed bytes. Ristretto encodings are
+/// canonical, so every representation of the same point compresses to the
+/// same bytes and the order survives decompress/compress cycles, making
+/// `RistrettoBoth` safe to use as a `BTreeMap` key.
 impl Ord for RistrettoBoth {
     fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
         self.compressed.0.cmp(&other.compressed.0)
@@ -215,3 +233,106 @@ impl ser::Readable for RistrettoBoth {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{ct_eq_bytes, RistrettoBoth};
+
+    #[test]
+    fn test_ct_eq_bytes() {
+        assert!(ct_eq_bytes(b"", b""));
+        assert!(ct_eq_bytes(b"mohan", b"mohan"));
+        assert!(!ct_eq_bytes(b"mohan", b"mohaN"));
+        assert!(!ct_eq_bytes(b"mohan", b"moha"));
+        assert!(!ct_eq_bytes(b"", b"m"));
+    }
+
+    #[cfg(all(feature = "rand_os", feature = "rand"))]
+    #[test]
+    fn test_random() {
+        let mut rng = crate::mohan_rand();
+        let a = RistrettoBoth::random(&mut rng);
+        let b = RistrettoBoth::random(&mut rng);
+        assert_ne!(a, b);
+        assert_eq!(a.as_compressed(), &a.as_point().compress());
+        assert_eq!(RistrettoBoth::from_bytes(&a.to_bytes()), Some(a));
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_bad_encodings() {
+        // Invalid encodings from the ristretto255 test vectors (RFC 9496, A.2)
+        let bad = [
+            // Non-canonical field encodings
+            "00ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            "f3ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            "edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            // Negative field elements
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "01ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            "ed57ffd8c914fb201471d1c3d245ce3c746fcbe63a3679d51b6a516ebebe0e20",
+            "c34c4e1826e5d403b78e246e88aa051c36ccf0aafebffe137d148a2bf9104562",
+            "c940e5a4404157cfb1628b108db051a8d439e1a421394ec4ebccb9ec92a8ac78",
+            "47cfc5497c53dc8e61c91d17fd626ffb1c49e2bca94eed052281b510b1117a24",
+            "f1c6165d33367351b0da8f6e4511010c68174a03b6581212c71c0e1d026c3c72",
+            "87260f7a2f12495118360f02c26a470f450dadf34a413d21042b43b9d93e1309",
+            // Non-square x^2
+            "26948d35ca62e643e26a83177332e6b6afeb9d08e4268b650f1f5bbd8d81d371",
+            "4eac077a713c57b4f4397629a4145982c661f48044dd3f96427d40b147d9742f",
+            "de6a7b00deadc788eb6b6c8d20c0ae96c2f2019078fa604fee5b87d6e989ad7b",
+            "bcab477be20861e01e4a0e295284146a510150d9817763caf1a6f4b422d67042",
+            "2a292df7e32cababbd9de088d1d1abec9fc0440f637ed2fba145094dc14bea08",
+            "f4a9e534fc0d216c44b218fa0c42d99635a0127ee2e53c712f70609649fdff22",
+            "8268436f8c4126196cf64b3c7ddbda90746a378625f9813dd9b8457077256731",
+            "2810e5cbc2cc4d4eece54f61c6f69758e289aa7ab440b3cbeaa21995c2f4232b",
+            // Negative xy value
+            "3eb858e78f5a7254d8c9731174a94f76755fd3941c0ac93735c07ba14579630e",
+            "a45fdc55c76448c049a1ab33f17023edfb2be3581e9c7aade8a6125215e04220",
+            "d483fe813c6ba647ebbfd3ec41adca1c6130c2beeee9d9bf065c8d151c5f396e",
+            "8a2e1d30050198c65a54483123960ccc38aef6848e1ec8f5f780e8523769ba32",
+            "32888462f8b486c68ad7dd9610be5192bbeaf3b443951ac1a8118419d9fa097b",
+            "227142501b9d4355ccba290404bde41575b037693cef1f438c47f8fbf35d1165",
+            "5c37cc491da847cfeb9281d407efc41e15144c876e0170b499a96a22ed31e01e",
+            "445425117cb8c90edcbc7c1cc0e74f747f2c1efa5630a967c64f287792a48a4b",
+            // s = -1, which causes y = 0
+            "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+        ];
+        for encoding in bad.iter() {
+            let bytes = crate::hex::from_hex(encoding.to_string()).unwrap();
+            assert!(RistrettoBoth::from_bytes(&bytes).is_none(), "{}", encoding);
+        }
+
+        // The identity is the all zero encoding and must still decode
+        assert!(RistrettoBoth::from_bytes(&[0u8; 32]).is_some());
+    }
+
+    #[cfg(all(feature = "rand_os", feature = "rand"))]
+    #[test]
+    fn test_btreemap_keys_survive_round_trip() {
+        use crate::dalek::constants::RISTRETTO_BASEPOINT_POINT;
+        use crate::dalek::scalar::Scalar;
+        use crate::ser::{deserialize_default, ser_vec, ProtocolVersion};
+        use std::collections::BTreeMap;
+
+        let mut rng = crate::mohan_rand();
+        let mut map = BTreeMap::new();
+        for i in 0..200u64 {
+            map.insert(RistrettoBoth::random(&mut rng), i);
+        }
+        assert_eq!(map.len(), 200);
+
+        for (key, value) in map.iter() {
+            let bytes = ser_vec(key, ProtocolVersion::local()).unwrap();
+            let back: RistrettoBoth = deserialize_default(&mut &bytes[..]).unwrap();
+            assert_eq!(map.get(&back), Some(value));
+        }
+
+        // The same point reached by different arithmetic is the same key
+        let a = Scalar::from(7u64) * RISTRETTO_BASEPOINT_POINT;
+        let b = Scalar::from(3u64) * RISTRETTO_BASEPOINT_POINT
+            + Scalar::from(4u64) * RISTRETTO_BASEPOINT_POINT;
+        let mut map = BTreeMap::new();
+        map.insert(RistrettoBoth::from_point(a), "seven");
+        assert_eq!(map.get(&RistrettoBoth::from_point(b)), Some(&"seven"));
+    }
+}
@@ -0,0 +1,382 @@
+// Copyright 2021 Stichting Organism
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! 256bit Unsigned Integer
+
+use subtle::{Choice, ConstantTimeEq};
+use thiserror::Error;
+
+uint::construct_uint! {
+    pub struct U256(4);
+}
+
+uint::construct_uint! {
+    /// 512bit unsigned integer, wide enough for the full product of two
+    /// `U256`s
+    pub struct U512(8);
+}
+
+/// U256 parsing errors, exported as `U256Error`
+#[derive(Copy, Clone, Debug, Eq, Error, PartialEq)]
+pub enum Error {
+    /// Input had no digits
+    #[error("no digits")]
+    Empty,
+
+    /// Input had something other than digits and a single decimal point
+    #[error("invalid character `{0}`")]
+    InvalidChar(char),
+
+    /// Input had more significant decimal places than allowed
+    #[error("more than {0} decimal places")]
+    TooPrecise(u32),
+
+    /// Value does not fit in 256 bits
+    #[error("overflow")]
+    Overflow,
+}
+
+impl U256 {
+    /// Build from 32 bytes, least significant byte first.
+    pub fn from_bytes_le(bytes: &[u8; 32]) -> U256 {
+        U256::from_little_endian(bytes)
+    }
+
+    /// Build from 32 bytes, most significant byte first. This is how
+    /// `H256::to_uint` reads a hash.
+    pub fn from_bytes_be(bytes: &[u8; 32]) -> U256 {
+        U256::from_big_endian(bytes)
+    }
+
+    /// Serialize to 32 bytes, least significant byte first.
+    pub fn to_le_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        self.to_little_endian(&mut bytes);
+        bytes
+    }
+
+    /// Serialize to 32 bytes, most significant byte first.
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        self.to_big_endian(&mut bytes);
+        bytes
+    }
+
+    /// Reverse the order of all 256 bits, the least significant bit becomes
+    /// the most significant.
+    pub fn reverse_bits(&self) -> U256 {
+        let mut words = [0u64; 4];
+        for (i, word) in self.0.iter().rev().enumerate() {
+            words[i] = word.reverse_bits();
+        }
+        U256(words)
+    }
+
+    /// Parse a decimal amount such as `"1.5"` into an integer count of the
+    /// smallest unit, where one whole unit is `10^decimals`. With 8 decimals
+    /// `"1.5"` gives 150000000. Trailing zeros past `decimals` places are
+    /// accepted, any other extra precision is an error rather than being
+    /// rounded away.
+    pub fn from_decimal_str(s: &str, decimals: u32) -> Result<U256, Error> {
+        let (int_part, frac_part) = match s.find('.') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, ""),
+        };
+        if int_part.is_empty() && frac_part.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        let mut value = U256::zero();
+        let push_digit = |value: U256, c: char| -> Result<U256, Error> {
+            let d = c.to_digit(10).ok_or(Error::InvalidChar(c))?;
+            value
+                .checked_mul(U256::from(10u8))
+                .and_then(|v| v.checked_add(U256::from(d)))
+                .ok_or(Error::Overflow)
+        };
+
+        for c in int_part.chars() {
+            value = push_digit(value, c)?;
+        }
+        for (i, c) in frac_part.chars().enumerate() {
+            if i < decimals as usize {
+                value = push_digit(value, c)?;
+            } else if c != '0' {
+                return Err(match c.to_digit(10) {
+                    Some(_) => Error::TooPrecise(decimals),
+                    None => Error::InvalidChar(c),
+                });
+            }
+        }
+        for _ in frac_part.len()..decimals as usize {
+            value = push_digit(value, '0')?;
+        }
+
+        Ok(value)
+    }
+
+    /// Format an integer count of the smallest unit as a decimal amount
+    /// with `decimals` places, dropping trailing zeros. With 8 decimals
+    /// 150000000 gives `"1.5"` and 100000000 gives `"1"`.
+    pub fn to_decimal_str(&self, decimals: u32) -> String {
+        let digits = self.to_string();
+        let decimals = decimals as usize;
+        if decimals == 0 {
+            return digits;
+        }
+
+        let padded = if digits.len() <= decimals {
+            format!("{}{}", "0".repeat(decimals + 1 - digits.len()), digits)
+        } else {
+            digits
+        };
+        let (int_part, frac_part) = padded.split_at(padded.len() - decimals);
+        let frac_part = frac_part.trim_end_matches('0');
+
+        if frac_part.is_empty() {
+            int_part.to_string()
+        } else {
+            format!("{}.{}", int_part, frac_part)
+        }
+    }
+
+    /// Constant time check for zero, for use on secret derived values
+    pub fn ct_is_zero(&self) -> Choice {
+        let acc = self.0.iter().fold(0u64, |acc, word| acc | word);
+        acc.ct_eq(&0)
+    }
+}
+
+impl From<U256> for U512 {
+    fn from(v: U256) -> U512 {
+        let mut words = [0u64; 8];
+        words[..4].copy_from_slice(&v.0);
+        U512(words)
+    }
+}
+
+impl U256 {
+    /// Multiply without overflow, returning the full 512bit product
+    pub fn full_mul(self, other: U256) -> U512 {
+        U512::from(self) * U512::from(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, U256, U512};
+
+    #[test]
+    fn test_bytes_endianness() {
+        let values = [
+            U256::zero(),
+            U256::one(),
+            U256::from(0x0102_0304_0506_0708u64) << 100,
+            U256::MAX,
+        ];
+        for x in values.iter() {
+            assert_eq!(U256::from_bytes_le(&x.to_le_bytes()), *x);
+            assert_eq!(U256::from_bytes_be(&x.to_be_bytes()), *x);
+        }
+
+        let mut bytes = [0u8; 32];
+        bytes[0] = 1;
+        assert_eq!(U256::from_bytes_le(&bytes), U256::one());
+        assert_eq!(U256::from_bytes_be(&bytes), U256::one() << 248);
+        assert_ne!(U256::from_bytes_le(&bytes), U256::from_bytes_be(&bytes));
+
+        let h = crate::hash::blake256(b"endianness");
+        assert_eq!(h.to_uint(), U256::from_bytes_be(h.as_fixed_bytes()));
+    }
+
+    #[test]
+    fn test_reverse_bits() {
+        assert_eq!(U256::one().reverse_bits(), U256::one() << 255);
+        assert_eq!(U256::zero().reverse_bits(), U256::zero());
+        assert_eq!(U256::MAX.reverse_bits(), U256::MAX);
+        assert_eq!((U256::one() << 70).reverse_bits(), U256::one() << 185);
+
+        let x = U256::from(0xDEAD_BEEF_u64) << 33;
+        assert_eq!(x.reverse_bits().reverse_bits(), x);
+    }
+
+    #[test]
+    fn test_decimal_str() {
+        let one_and_half = U256::from(150_000_000u64);
+        assert_eq!(U256::from_decimal_str("1.5", 8), Ok(one_and_half));
+        assert_eq!(U256::from_decimal_str("1.50000000000", 8), Ok(one_and_half));
+        assert_eq!(U256::from_decimal_str("001.5", 8), Ok(one_and_half));
+        assert_eq!(one_and_half.to_decimal_str(8), "1.5");
+
+        assert_eq!(
+            U256::from_decimal_str("21", 8),
+            Ok(U256::from(2_100_000_000u64))
+        );
+        assert_eq!(U256::from(2_100_000_000u64).to_decimal_str(8), "21");
+        assert_eq!(U256::from_decimal_str(".00000001", 8), Ok(U256::one()));
+        assert_eq!(U256::one().to_decimal_str(8), "0.00000001");
+        assert_eq!(U256::zero().to_decimal_str(8), "0");
+        assert_eq!(U256::from(42u8).to_decimal_str(0), "42");
+
+        assert_eq!(
+            U256::from_decimal_str("1.000000001", 8),
+            Err(Error::TooPrecise(8))
+        );
+        assert_eq!(U256::from_decimal_str("1.5", 0), Err(Error::TooPrecise(0)));
+        assert_eq!(U256::from_decimal_str("", 8), Err(Error::Empty));
+        assert_eq!(U256::from_decimal_str(".", 8), Err(Error::Empty));
+        assert_eq!(
+            U256::from_decimal_str("1.2.3", 8),
+            Err(Error::InvalidChar('.'))
+        );
+        assert_eq!(
+            U256::from_decimal_str("-1", 8),
+            Err(Error::InvalidChar('-'))
+        );
+
+        let max = U256::MAX.to_string();
+        assert_eq!(U256::from_decimal_str(&max, 0), Ok(U256::MAX));
+        assert_eq!(U256::from_decimal_str(&max, 1), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn test_ct_is_zero() {
+        let values = [
+            U256::zero(),
+            U256::one(),
+            U256::from(0xFFu64) << 200,
+            U256::MAX,
+        ];
+        for v in values.iter() {
+            assert_eq!(bool::from(v.ct_is_zero()), v.is_zero());
+        }
+        assert!(bool::from(U256::zero().ct_is_zero()));
+    }
+
+    #[test]
+    fn test_assign_ops() {
+        // The assign operators come with `construct_uint!`
+        let mut sum = U256::zero();
+        for i in 1..=100u64 {
+            sum += U256::from(i) << 190;
+        }
+        let expected = (1..=100u64).fold(U256::zero(), |acc, i| acc + (U256::from(i) << 190));
+        assert_eq!(sum, expected);
+        assert_eq!(sum, U256::from(5050u64) << 190);
+
+        sum -= U256::from(50u64) << 190;
+        assert_eq!(sum, U256::from(5000u64) << 190);
+
+        let mut prod = U256::one();
+        for _ in 0..4 {
+            prod *= U256::from(u64::MAX);
+        }
+        let max = U256::from(u64::MAX);
+        assert_eq!(prod, max * max * max * max);
+
+        prod /= max;
+        assert_eq!(prod, max * max * max);
+    }
+
+    #[test]
+    fn test_u512_bits() {
+        assert_eq!(U512::zero().bits(), 0);
+        assert_eq!(U512::one().bits(), 1);
+        assert_eq!(U512::from(255u64).bits(), 8);
+        assert_eq!((U512::one() << 300).bits(), 301);
+        assert_eq!(U512::MAX.bits(), 512);
+        assert!((U512::one() << 511).bit(511));
+        assert!(!(U512::one() << 511).bit(510));
+        assert_eq!((U512::one() << 300).leading_zeros(), 211);
+        assert_eq!((U512::one() << 300).trailing_zeros(), 300);
+    }
+
+    #[test]
+    fn test_u512_display_and_cmp() {
+        assert_eq!(U512::from(0u64).to_string(), "0");
+        assert_eq!(U512::from(1_000_000u64).to_string(), "1000000");
+        assert_eq!(
+            U512::MAX.to_string(),
+            "13407807929942597099574024998205846127479365820592393377723561443721764030073546976801874298166903427690031858186486050853753882811946569946433649006084095"
+        );
+        assert_eq!(
+            format!("{:x}", U512::one() << 256),
+            format!("1{}", "0".repeat(64))
+        );
+
+        assert!(U512::one() << 256 > U512::from(u64::MAX));
+        assert!(U512::one() < U512::from(2u64));
+        assert!(U512::MAX > U512::one() << 511);
+        assert_eq!(U512::from(U256::MAX), (U512::one() << 256) - U512::one());
+    }
+
+    #[test]
+    fn test_u512_arithmetic() {
+        let a = U512::one() << 300;
+        let b = U512::from(12345u64);
+
+        assert_eq!(a + b - b, a);
+        assert_eq!((a * b) / b, a);
+        assert_eq!((a * b) % b, U512::zero());
+        assert_eq!(a / (U512::one() << 299), U512::from(2u64));
+        assert_eq!((a + b) % a, b);
+
+        let big = U512::from(u64::MAX);
+        assert_eq!(
+            big * big,
+            U512::from(U256::from(u64::MAX) * U256::from(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn test_u512_checked() {
+        let half = U512::one() << 511;
+
+        assert_eq!(half.checked_add(half - U512::one()), Some(U512::MAX));
+        assert_eq!(half.checked_add(half), None);
+        assert_eq!(U512::MAX.checked_add(U512::one()), None);
+
+        assert_eq!(half.checked_sub(half), Some(U512::zero()));
+        assert_eq!(U512::zero().checked_sub(U512::one()), None);
+
+        assert_eq!(
+            (U512::one() << 255).checked_mul(U512::one() << 256),
+            Some(half)
+        );
+        assert_eq!((U512::one() << 256).checked_mul(U512::one() << 256), None);
+
+        assert_eq!(U512::one().checked_div(U512::zero()), None);
+    }
+
+    #[test]
+    fn test_u512_extreme_shift() {
+        assert_eq!(U512::one() << 511 >> 511, U512::one());
+        assert_eq!(U512::MAX << 512, U512::zero());
+        assert_eq!(U512::MAX >> 512, U512::zero());
+        assert_eq!(U512::MAX >> 511, U512::one());
+        assert_eq!(U512::MAX << 511, U512::one() << 511);
+    }
+
+    #[test]
+    fn test_full_mul() {
+        // (2^256 - 1)^2 = 2^512 - 2^257 + 1
+        assert_eq!(
+            U256::MAX.full_mul(U256::MAX),
+            U512::MAX - (U512::one() << 257) + U512::from(2u64)
+        );
+        let a = U256::from(u64::MAX) << 192;
+        assert_eq!(a.full_mul(U256::from(4u64)), U512::from(a) << 2);
+        assert_eq!(U256::zero().full_mul(U256::MAX), U512::zero());
+    }
+}
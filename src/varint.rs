@@ -13,8 +13,15 @@
 //
 
 //! BTC Style VarInt
+//!
+//! This is Bitcoin's CompactSize encoding: values below 0xFD take a single
+//! byte, larger ones a 0xFD/0xFE/0xFF marker followed by a little-endian
+//! u16/u32/u64. It is not LEB128, see `leb128::Leb128` for that.
 
-use crate::ser::{Error, Readable, Reader, Writeable, Writer};
+use crate::ser::{
+    BinReader, BinWriter, Error, ProtocolVersion, Readable, Reader, Writeable, Writer,
+};
+use std::io;
 
 /// A variable-length unsigned integer
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
@@ -26,18 +33,20 @@ impl VarInt {
     /// and 9 otherwise.
     #[inline]
     pub fn len(&self) -> usize {
-        match self.0 {
-            0..=0xFC => 1,
-            0xFD..=0xFFFF => 3,
-            0x10000..=0xFFFFFFFF => 5,
-            _ => 9,
-        }
+        encoded_len(self.0)
     }
 
     #[inline]
     pub fn as_u64(&self) -> u64 {
         self.0
     }
+
+    /// Read a `VarInt` from any `io::Read`, such as a socket, with the same
+    /// canonical encoding checks as `Readable::read`.
+    pub fn read_from<R: io::Read>(r: &mut R) -> Result<VarInt, Error> {
+        let mut reader = BinReader::new(r, ProtocolVersion::local());
+        VarInt::read(&mut reader)
+    }
 }
 
 // //TODO chheck if this cause issues
@@ -88,6 +97,9 @@ impl Readable for VarInt {
     }
 }
 
+/// Multi-byte values follow their marker byte in little-endian order, as
+/// Bitcoin's CompactSize does. This does not depend on the `Writer`: every
+/// `Writer` and `Reader` in `ser` is little-endian.
 impl Writeable for VarInt {
     fn write<W: Writer>(&self, writer: &mut W) -> Result<(), Error> {
         match self.0 {
@@ -114,6 +126,60 @@ impl Writeable for VarInt {
     }
 }
 
+/// Length of `n` once encoded as a `VarInt`, without building one.
+#[inline]
+pub const fn encoded_len(n: u64) -> usize {
+    match n {
+        0..=0xFC => 1,
+        0xFD..=0xFFFF => 3,
+        0x10000..=0xFFFFFFFF => 5,
+        _ => 9,
+    }
+}
+
+/// Encode a sequence of integers as back to back `VarInt`s.
+pub fn encode_all(values: impl Iterator<Item = u64>) -> Vec<u8> {
+    let mut buf = vec![];
+    let mut writer = BinWriter::default(&mut buf);
+    for v in values {
+        VarInt(v)
+            .write(&mut writer)
+            .expect("writing to a Vec can not fail");
+    }
+    buf
+}
+
+/// Lazily decodes back to back `VarInt`s from a byte slice, as written by
+/// `encode_all`. Iteration stops at the end of the slice, or after the
+/// first error.
+pub struct DecodeIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DecodeIter<'a> {
+    /// Iterate over the `VarInt`s in `data`
+    pub fn new(data: &'a [u8]) -> DecodeIter<'a> {
+        DecodeIter { data }
+    }
+}
+
+impl<'a> Iterator for DecodeIter<'a> {
+    type Item = Result<u64, Error>;
+
+    fn next(&mut self) -> Option<Result<u64, Error>> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let mut reader = BinReader::new(&mut self.data, ProtocolVersion::local());
+        let res = VarInt::read(&mut reader).map(|v| v.0);
+        if res.is_err() {
+            self.data = &[];
+        }
+        Some(res)
+    }
+}
+
 #[test]
 fn serialize_varint_test() {
     use crate::ser::{ser_vec, ProtocolVersion};
@@ -142,3 +208,85 @@ fn serialize_varint_test() {
         vec![0xFFu8, 0xE0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0, 0]
     );
 }
+
+#[test]
+fn encode_decode_all_test() {
+    let values = vec![
+        0,
+        0xFC,
+        0xFD,
+        0xFFFF,
+        0x10000,
+        0xFFFFFFFF,
+        0x100000000,
+        u64::MAX,
+    ];
+    let encoded = encode_all(values.iter().cloned());
+    assert_eq!(encoded.len(), 1 + 1 + 3 + 3 + 5 + 5 + 9 + 9);
+
+    let decoded = DecodeIter::new(&encoded)
+        .collect::<Result<Vec<u64>, Error>>()
+        .unwrap();
+    assert_eq!(decoded, values);
+
+    // Truncated input yields an error, then stops
+    let mut iter = DecodeIter::new(&encoded[..encoded.len() - 1]);
+    assert_eq!(iter.by_ref().filter(|r| r.is_err()).count(), 1);
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn read_from_test() {
+    use std::io::Cursor;
+
+    for &n in [0xFCu64, 0xFFFF, 0xFFFFFFFF, u64::MAX].iter() {
+        let bytes = encode_all(std::iter::once(n));
+        let mut cursor = Cursor::new(bytes);
+        assert_eq!(VarInt::read_from(&mut cursor).unwrap(), VarInt(n));
+    }
+
+    // Non canonical, 0xFC must be a single byte
+    let mut cursor = Cursor::new(vec![0xFDu8, 0xFC, 0]);
+    assert_eq!(VarInt::read_from(&mut cursor), Err(Error::InvalidVarInt));
+}
+
+#[test]
+fn encoded_len_test() {
+    const LENS: [usize; 8] = [
+        encoded_len(0),
+        encoded_len(0xFC),
+        encoded_len(0xFD),
+        encoded_len(0xFFFF),
+        encoded_len(0x10000),
+        encoded_len(0xFFFFFFFF),
+        encoded_len(0x100000000),
+        encoded_len(u64::MAX),
+    ];
+    assert_eq!(LENS, [1, 1, 3, 3, 5, 5, 9, 9]);
+
+    for &n in [0xFCu64, 0xFD, 0x10000, u64::MAX].iter() {
+        assert_eq!(encoded_len(n), encode_all(std::iter::once(n)).len());
+    }
+}
+
+#[test]
+fn varint_little_endian_test() {
+    use crate::ser::{deserialize_default, ser_vec, ProtocolVersion};
+
+    let cases: [(u64, &[u8]); 3] = [
+        (0x0102, &[0xFD, 0x02, 0x01]),
+        (0x01020304, &[0xFE, 0x04, 0x03, 0x02, 0x01]),
+        (
+            0x0102030405060708,
+            &[0xFF, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01],
+        ),
+    ];
+    for (n, bytes) in cases.iter() {
+        assert_eq!(
+            &ser_vec(&VarInt(*n), ProtocolVersion::local()).unwrap()[..],
+            *bytes
+        );
+        let back: VarInt = deserialize_default(&mut &bytes[..]).unwrap();
+        assert_eq!(back, VarInt(*n));
+    }
+}